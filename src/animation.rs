@@ -1,6 +1,18 @@
-use crate::item::Item;
-use crate::player::Player;
+use crate::animation_state::{
+    measure_velocity, scale_playback_speed, AnimationParams, StateMachine,
+};
+use crate::animator::{FrameEvent, LoopMode, SpeedScale, SpriteAnimator, SpriteClip};
+use crate::atlas::{AtlasPacker, SheetGrid};
+use crate::dash::Dash;
+use crate::health::Dead;
+use crate::platformer::PlatformerBody;
+use crate::player::{ControlMode, Player};
+use crate::stamina::Stamina;
+use bevy::asset::{AssetLoader, LoadContext, LoadState, LoadedAsset};
 use bevy::prelude::*;
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::utils::BoxedFuture;
+use serde::Deserialize;
 use std::collections::HashMap;
 
 #[derive(Component)]
@@ -8,23 +20,66 @@ pub struct AnimationPlugin;
 
 impl Plugin for AnimationPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<PlayerAnimations>()
-            .add_systems(Update, animate_player)
-            .add_systems(Update, update_player_animation)
-            .add_systems(Update, animate_item_idle)
-            .add_systems(Update, animate_item_in_inv);
+        app.add_asset::<AnimationManifest>()
+            .init_asset_loader::<AnimationManifestLoader>()
+            .init_resource::<PlayerAnimations>()
+            .add_systems(Update, (load_player_sheets, build_player_animations).chain())
+            .add_systems(
+                Update,
+                (
+                    measure_velocity,
+                    update_player_params,
+                    update_player_animation,
+                    scale_playback_speed,
+                )
+                    .chain(),
+            );
     }
 }
 
+// What the player is doing, regardless of direction. Keys of an animation manifest's states.
+#[derive(Deserialize, Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum AnimationState {
+    Idle,
+    Walk,
+    Run,
+    Dash,
+    Death,
+}
+
 // Eq, PartialEq, and Hash necessary for animation to be inserted into HashMap world resource.
-#[derive(Component, Eq, PartialEq, Hash, Clone, Copy)]
+#[derive(Component, Eq, PartialEq, Hash, Clone, Copy, Debug)]
 pub enum PlayerAnimationType {
     Idle(Direction),
     Walk(Direction),
     Run(Direction),
+    Dash(Direction),
+    Death(Direction), // No frames yet, add a Death state to the manifest to play on death.
 }
 
-#[derive(Component, Eq, PartialEq, Hash, Clone, Copy, Debug)]
+impl PlayerAnimationType {
+    pub fn new(state: AnimationState, direction: Direction) -> Self {
+        match state {
+            AnimationState::Idle => PlayerAnimationType::Idle(direction),
+            AnimationState::Walk => PlayerAnimationType::Walk(direction),
+            AnimationState::Run => PlayerAnimationType::Run(direction),
+            AnimationState::Dash => PlayerAnimationType::Dash(direction),
+            AnimationState::Death => PlayerAnimationType::Death(direction),
+        }
+    }
+
+    pub fn state(self) -> AnimationState {
+        match self {
+            PlayerAnimationType::Idle(_) => AnimationState::Idle,
+            PlayerAnimationType::Walk(_) => AnimationState::Walk,
+            PlayerAnimationType::Run(_) => AnimationState::Run,
+            PlayerAnimationType::Dash(_) => AnimationState::Dash,
+            PlayerAnimationType::Death(_) => AnimationState::Death,
+        }
+    }
+}
+
+#[derive(Component, Deserialize, Eq, PartialEq, Hash, Clone, Copy, Debug)]
 pub enum Direction {
     North,
     East,
@@ -36,382 +91,649 @@ pub enum Direction {
     SouthEast,
 }
 
-#[derive(Component, Clone, Debug)]
-pub struct PlayerAnimation {
-    pub len: usize,
-    pub frame_time: f32,
-    pub path: String,
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::East,
+        Direction::West,
+        Direction::South,
+        Direction::NorthWest,
+        Direction::NorthEast,
+        Direction::SouthWest,
+        Direction::SouthEast,
+    ];
+
+    // Direction facing the other way horizontally, None for North and South.
+    pub fn mirrored(self) -> Option<Direction> {
+        match self {
+            Direction::East => Some(Direction::West),
+            Direction::West => Some(Direction::East),
+            Direction::NorthEast => Some(Direction::NorthWest),
+            Direction::NorthWest => Some(Direction::NorthEast),
+            Direction::SouthEast => Some(Direction::SouthWest),
+            Direction::SouthWest => Some(Direction::SouthEast),
+            Direction::North | Direction::South => None,
+        }
+    }
+
+    // Nearest of the four cardinal directions, diagonals favor their horizontal side.
+    pub fn to_four(self) -> Direction {
+        match self {
+            Direction::NorthEast | Direction::SouthEast => Direction::East,
+            Direction::NorthWest | Direction::SouthWest => Direction::West,
+            direction => direction,
+        }
+    }
+
+    // Unit vector pointing the way this direction faces.
+    pub fn to_vec2(self) -> Vec2 {
+        match self {
+            Direction::North => Vec2::new(0., 1.),
+            Direction::East => Vec2::new(1., 0.),
+            Direction::West => Vec2::new(-1., 0.),
+            Direction::South => Vec2::new(0., -1.),
+            Direction::NorthWest => Vec2::new(-1., 1.).normalize(),
+            Direction::NorthEast => Vec2::new(1., 1.).normalize(),
+            Direction::SouthWest => Vec2::new(-1., -1.).normalize(),
+            Direction::SouthEast => Vec2::new(1., -1.).normalize(),
+        }
+    }
+
+    // Snap a vector to nearest of the eight directions, None for a zero vector.
+    pub fn from_vec2(vec: Vec2) -> Option<Direction> {
+        if vec == Vec2::ZERO {
+            return None;
+        }
+
+        // Split the circle into eight 45 degree slices, starting at East and going counter-clockwise.
+        let slice = (vec.y.atan2(vec.x) / std::f32::consts::FRAC_PI_4).round() as i32;
+
+        Some(match slice.rem_euclid(8) {
+            0 => Direction::East,
+            1 => Direction::NorthEast,
+            2 => Direction::North,
+            3 => Direction::NorthWest,
+            4 => Direction::West,
+            5 => Direction::SouthWest,
+            6 => Direction::South,
+            _ => Direction::SouthEast,
+        })
+    }
+}
+
+// How to fill in directions a state has no spritesheet for, so artists can ship partial sets.
+// Options are tried in order: mirroring, collapsing to four directions, then the default direction.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct FallbackPolicy {
+    pub mirror: bool, // Flip the opposite horizontal direction, e.g. East for West.
+    pub four_directions: bool, // Use the nearest cardinal direction for diagonals.
+    pub default_direction: Option<Direction>,
+}
+
+impl Default for FallbackPolicy {
+    fn default() -> Self {
+        FallbackPolicy {
+            mirror: true,
+            four_directions: true,
+            default_direction: Some(Direction::South),
+        }
+    }
+}
+
+impl FallbackPolicy {
+    // Find a direction with a clip to stand in for a missing one, and whether it has to be flipped.
+    pub fn resolve(
+        &self,
+        direction: Direction,
+        has_clip: impl Fn(Direction) -> bool,
+    ) -> Option<(Direction, bool)> {
+        let mirrored = |direction: Direction| {
+            direction
+                .mirrored()
+                .filter(|mirrored| self.mirror && has_clip(*mirrored))
+                .map(|mirrored| (mirrored, true))
+        };
+
+        if let Some(found) = mirrored(direction) {
+            return Some(found);
+        }
+
+        if self.four_directions {
+            let collapsed = direction.to_four();
+            if collapsed != direction {
+                if has_clip(collapsed) {
+                    return Some((collapsed, false));
+                }
+                if let Some(found) = mirrored(collapsed) {
+                    return Some(found);
+                }
+            }
+        }
+
+        self.default_direction
+            .filter(|default| has_clip(*default))
+            .map(|default| (default, false))
+    }
 }
 
+// Player's clips, all sharing one atlas built once the manifest and its spritesheets have loaded.
 #[derive(Resource)]
 pub struct PlayerAnimations {
-    pub map: HashMap<PlayerAnimationType, PlayerAnimation>,
+    pub manifest: Handle<AnimationManifest>,
+    pub map: HashMap<PlayerAnimationType, SpriteClip>,
+    pub state_machine: StateMachine,
+    sheets: HashMap<String, Handle<Image>>, // Every spritesheet the manifest uses, by path.
+    pending: bool, // Manifest or a sheet changed, rebuild once all sheets are loaded.
 }
 
 impl PlayerAnimations {
-    pub fn add(&mut self, id: PlayerAnimationType, animation: PlayerAnimation) {
-        self.map.insert(id, animation);
+    pub fn add(&mut self, id: PlayerAnimationType, clip: SpriteClip) {
+        self.map.insert(id, clip);
     }
 
-    pub fn get(&self, id: PlayerAnimationType) -> Option<PlayerAnimation> {
-        self.map.get(&id).cloned()
+    pub fn get(&self, id: PlayerAnimationType) -> Option<&SpriteClip> {
+        self.map.get(&id)
     }
 }
 
-// Initialize a Bevy resource for player's animations, and add each animation to resource.
+// Start loading player's animation manifest, animations get filled in once it's loaded.
 impl FromWorld for PlayerAnimations {
-    fn from_world(_world: &mut World) -> Self {
-        const IDLE_FRAME_TIME: f32 = 0.15;
-        const WALK_FRAME_TIME: f32 = 0.08;
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
 
-        let mut map = PlayerAnimations {
+        PlayerAnimations {
+            manifest: asset_server.load("player/player.anim.ron"),
             map: HashMap::new(),
+            state_machine: StateMachine::default(),
+            sheets: HashMap::new(),
+            pending: false,
+        }
+    }
+}
+
+// Animation set for a character, loaded from a .anim.ron file. New characters only need a new
+// manifest and spritesheets.
+#[derive(Deserialize, TypeUuid, TypePath, Debug)]
+#[uuid = "5b0f2c53-8f2e-4c43-9a4e-0d6f7f1d6c2a"]
+pub struct AnimationManifest {
+    pub frame_size: Option<(f32, f32)>, // Size of a frame in every sheet, worked out per sheet if left out.
+    pub states: HashMap<AnimationState, ManifestState>,
+    #[serde(default)]
+    pub fallback: FallbackPolicy,
+    #[serde(default)]
+    pub state_machine: StateMachine,
+    #[serde(default)]
+    pub hands: HashMap<Direction, (f32, f32)>, // Where held items go, from frame center in sheet pixels, y up.
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ManifestState {
+    pub fps: f32,
+    #[serde(default)]
+    pub loop_mode: LoopMode,
+    pub frame_size: Option<(f32, f32)>, // Overrides manifest's frame_size for this state.
+    #[serde(default)]
+    pub events: HashMap<String, Vec<usize>>, // Event name to frames it fires on.
+    pub speed_scale: Option<SpeedScale>,
+    #[serde(default)]
+    pub hands: HashMap<Direction, Vec<(f32, f32)>>, // Hands for each frame, for states where they move.
+    pub directions: HashMap<Direction, ManifestClip>,
+}
+
+// Single spritesheet. Its grid comes from the image size and frame size, or from frames if
+// there's no frame size, and frames is checked against the sheet when both are given.
+#[derive(Deserialize, Debug)]
+pub struct ManifestClip {
+    pub path: String,
+    pub frames: Option<usize>,
+}
+
+#[derive(Default)]
+pub struct AnimationManifestLoader;
+
+impl AssetLoader for AnimationManifestLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let manifest = ron::de::from_bytes::<AnimationManifest>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(manifest));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["anim.ron"]
+    }
+}
+
+// Start loading every spritesheet whenever the manifest loads or changes on disk, or rebuild
+// when one of the sheets itself changes.
+fn load_player_sheets(
+    mut manifest_events: EventReader<AssetEvent<AnimationManifest>>,
+    mut image_events: EventReader<AssetEvent<Image>>,
+    manifests: Res<Assets<AnimationManifest>>,
+    asset_server: Res<AssetServer>,
+    mut animation_res: ResMut<PlayerAnimations>,
+) {
+    let manifest_changed = manifest_events.iter().any(|ev| match ev {
+        AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+            *handle == animation_res.manifest
+        }
+        AssetEvent::Removed { .. } => false,
+    });
+
+    let sheet_changed = image_events.iter().any(|ev| match ev {
+        AssetEvent::Modified { handle } => {
+            animation_res.sheets.values().any(|sheet| sheet == handle)
+        }
+        _ => false,
+    });
+
+    if manifest_changed {
+        let Some(manifest) = manifests.get(&animation_res.manifest) else {
+            return;
         };
 
-        map.add(
-            PlayerAnimationType::Idle(Direction::South),
-            PlayerAnimation {
-                len: 6,
-                frame_time: IDLE_FRAME_TIME,
-                path: "player/idle/idle_south".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Idle(Direction::SouthWest),
-            PlayerAnimation {
-                len: 3,
-                frame_time: IDLE_FRAME_TIME,
-                path: "player/idle/idle_southwest".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Idle(Direction::West),
-            PlayerAnimation {
-                len: 6,
-                frame_time: IDLE_FRAME_TIME,
-                path: "player/idle/idle_west".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Idle(Direction::NorthWest),
-            PlayerAnimation {
-                len: 6,
-                frame_time: IDLE_FRAME_TIME,
-                path: "player/idle/idle_northwest".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Idle(Direction::North),
-            PlayerAnimation {
-                len: 6,
-                frame_time: IDLE_FRAME_TIME,
-                path: "player/idle/idle_north".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Idle(Direction::NorthEast),
-            PlayerAnimation {
-                len: 6,
-                frame_time: IDLE_FRAME_TIME,
-                path: "player/idle/idle_northeast".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Idle(Direction::East),
-            PlayerAnimation {
-                len: 6,
-                frame_time: IDLE_FRAME_TIME,
-                path: "player/idle/idle_east".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Idle(Direction::SouthEast),
-            PlayerAnimation {
-                len: 6,
-                frame_time: IDLE_FRAME_TIME,
-                path: "player/idle/idle_southeast".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Walk(Direction::South),
-            PlayerAnimation {
-                len: 6,
-                frame_time: WALK_FRAME_TIME,
-                path: "player/walk/walk_south".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Walk(Direction::SouthWest),
-            PlayerAnimation {
-                len: 6,
-                frame_time: WALK_FRAME_TIME,
-                path: "player/walk/walk_southwest".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Walk(Direction::West),
-            PlayerAnimation {
-                len: 6,
-                frame_time: WALK_FRAME_TIME,
-                path: "player/walk/walk_west".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Walk(Direction::NorthWest),
-            PlayerAnimation {
-                len: 6,
-                frame_time: WALK_FRAME_TIME,
-                path: "player/walk/walk_northwest".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Walk(Direction::North),
-            PlayerAnimation {
-                len: 6,
-                frame_time: WALK_FRAME_TIME,
-                path: "player/walk/walk_north".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Walk(Direction::NorthEast),
-            PlayerAnimation {
-                len: 6,
-                frame_time: WALK_FRAME_TIME,
-                path: "player/walk/walk_northeast".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Walk(Direction::East),
-            PlayerAnimation {
-                len: 6,
-                frame_time: WALK_FRAME_TIME,
-                path: "player/walk/walk_east".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Walk(Direction::SouthEast),
-            PlayerAnimation {
-                len: 6,
-                frame_time: WALK_FRAME_TIME,
-                path: "player/walk/walk_southeast".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Run(Direction::South),
-            PlayerAnimation {
-                len: 6,
-                frame_time: WALK_FRAME_TIME,
-                path: "player/run/run_south".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Run(Direction::SouthEast),
-            PlayerAnimation {
-                len: 6,
-                frame_time: WALK_FRAME_TIME,
-                path: "player/run/run_southeast".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Run(Direction::SouthWest),
-            PlayerAnimation {
-                len: 6,
-                frame_time: WALK_FRAME_TIME,
-                path: "player/run/run_southwest".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Run(Direction::North),
-            PlayerAnimation {
-                len: 6,
-                frame_time: WALK_FRAME_TIME,
-                path: "player/run/run_north".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Run(Direction::NorthWest),
-            PlayerAnimation {
-                len: 6,
-                frame_time: WALK_FRAME_TIME,
-                path: "player/run/run_northwest".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Run(Direction::NorthEast),
-            PlayerAnimation {
-                len: 6,
-                frame_time: WALK_FRAME_TIME,
-                path: "player/run/run_northeast".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Run(Direction::East),
-            PlayerAnimation {
-                len: 6,
-                frame_time: WALK_FRAME_TIME,
-                path: "player/run/run_east".to_string(),
-            },
-        );
-
-        map.add(
-            PlayerAnimationType::Run(Direction::West),
-            PlayerAnimation {
-                len: 6,
-                frame_time: WALK_FRAME_TIME,
-                path: "player/run/run_west".to_string(),
-            },
-        );
-
-        map
+        let sheets = manifest
+            .states
+            .values()
+            .flat_map(|state_def| state_def.directions.values())
+            .map(|clip| (clip.path.clone(), asset_server.load(&clip.path)))
+            .collect();
+
+        animation_res.sheets = sheets;
+    }
+
+    if manifest_changed || sheet_changed {
+        animation_res.pending = true;
     }
 }
 
-// Animation logic for animating player.
-fn animate_player(
-    mut player_q: Query<&mut Player>,
-    mut sprite_q: Query<&mut TextureAtlasSprite, With<Player>>,
-    time: Res<Time>,
-    animation_res: Res<PlayerAnimations>,
+// Build player's animations once every spritesheet has loaded, with each sheet's grid worked out
+// from its size and every frame packed into one atlas. Clips that don't fit their sheet are
+// reported and left out.
+fn build_player_animations(
+    manifests: Res<Assets<AnimationManifest>>,
+    mut images: ResMut<Assets<Image>>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut animation_res: ResMut<PlayerAnimations>,
 ) {
-    let mut player = player_q.single_mut();
-    let mut sprite = sprite_q.single_mut();
+    if !animation_res.pending {
+        return;
+    }
 
-    player.frame_time += time.delta_seconds();
+    let loading = animation_res.sheets.values().any(|sheet| {
+        matches!(
+            asset_server.get_load_state(sheet),
+            LoadState::NotLoaded | LoadState::Loading
+        )
+    });
+    if loading {
+        return;
+    }
 
-    let Some(animation) = animation_res.get(player.animation) else {
+    let Some(manifest) = manifests.get(&animation_res.manifest) else {
         return;
     };
 
-    if player.frame_time > animation.frame_time {
-        let frames_elapsed = player.frame_time / animation.frame_time;
+    animation_res.pending = false;
+    animation_res.map.clear();
+    animation_res.state_machine = manifest.state_machine.clone();
+
+    let mut problems = Vec::new();
+    let mut packer = AtlasPacker::default();
+    let mut clips = Vec::new(); // Clips waiting on the atlas, with the sheet their frames come from.
+
+    for (state, state_def) in manifest.states.iter() {
+        let frame_size = state_def
+            .frame_size
+            .or(manifest.frame_size)
+            .map(|(width, height)| Vec2::new(width, height));
+
+        let events: Vec<FrameEvent> = state_def
+            .events
+            .iter()
+            .flat_map(|(name, frames)| {
+                frames.iter().map(|frame| FrameEvent {
+                    frame: *frame,
+                    name: name.clone(),
+                })
+            })
+            .collect();
+
+        for (direction, clip) in state_def.directions.iter() {
+            let id = PlayerAnimationType::new(*state, *direction);
+            let sheet = &animation_res.sheets[&clip.path];
+
+            let Some(image) = images.get(sheet) else {
+                problems.push(format!("{:?}: couldn't load {}", id, clip.path));
+                continue;
+            };
+
+            let grid = match SheetGrid::detect(image.size(), frame_size, clip.frames) {
+                Ok(grid) => grid,
+                Err(err) => {
+                    problems.push(format!("{:?}: {}, {}", id, clip.path, err));
+                    continue;
+                }
+            };
+
+            let len = match frame_count(clip.frames, grid.frame_count()) {
+                Ok(len) => len,
+                Err(err) => {
+                    problems.push(format!("{:?}: {}, {}", id, clip.path, err));
+                    continue;
+                }
+            };
+            if len != grid.frame_count() {
+                problems.push(format!(
+                    "{:?}: {} declares {} frames, sheet has {}",
+                    id,
+                    clip.path,
+                    len,
+                    grid.frame_count()
+                ));
+            }
+
+            if len == 0 {
+                continue;
+            }
+
+            // Per frame hands for this state, or the same spot for every frame.
+            let hands = match state_def.hands.get(direction) {
+                Some(frames) => frames.iter().map(|(x, y)| Vec2::new(*x, *y)).collect(),
+                None => manifest.hands.get(direction).map(|(x, y)| vec![Vec2::new(*x, *y)]).unwrap_or_default(),
+            };
+
+            // Several animations can share a spritesheet, its frames are only packed once.
+            packer.add_sheet(sheet, grid, len, &mut images);
+
+            clips.push((
+                id,
+                sheet.clone(),
+                SpriteClip {
+                    atlas: Handle::default(),
+                    len,
+                    frames: Vec::new(),
+                    frame_time: 1. / state_def.fps,
+                    loop_mode: state_def.loop_mode,
+                    events: events.clone(),
+                    flip_x: false,
+                    speed_scale: state_def.speed_scale,
+                    hands,
+                },
+            ));
+        }
+    }
+
+    let packed = match packer.finish(&mut images, &mut texture_atlases) {
+        Ok(packed) => packed,
+        Err(err) => {
+            error!("Couldn't pack player spritesheets into an atlas: {:?}", err);
+            return;
+        }
+    };
+
+    for (id, sheet, mut clip) in clips {
+        let Some(frames) = packed.frames(&sheet, clip.len) else {
+            problems.push(format!("{:?}: frames missing from atlas", id));
+            continue;
+        };
+
+        clip.atlas = packed.atlas.clone();
+        clip.frames = frames;
+        animation_res.add(id, clip);
+    }
 
-        // Animate!
-        sprite.index += frames_elapsed as usize;
+    if !problems.is_empty() {
+        problems.sort();
+        warn!("Problems with player spritesheets:\n  {}", problems.join("\n  "));
+    }
 
-        // If sprite index becomes greater than length of total animation frames, reset sprite index. (Restart animation)
-        if sprite.index >= animation.len {
-            sprite.index %= animation.len;
+    // Fill in missing directions from the ones that exist, and report what was filled in.
+    let mut report = Vec::new();
+
+    for state in manifest.states.keys() {
+        for direction in Direction::ALL {
+            let id = PlayerAnimationType::new(*state, direction);
+            if animation_res.map.contains_key(&id) {
+                continue;
+            }
+
+            let has_clip = |direction| {
+                animation_res
+                    .map
+                    .contains_key(&PlayerAnimationType::new(*state, direction))
+            };
+
+            let Some((source, flip_x)) = manifest.fallback.resolve(direction, has_clip) else {
+                report.push(format!("{:?}: missing, no fallback", id));
+                continue;
+            };
+
+            let source_id = PlayerAnimationType::new(*state, source);
+            let mut clip = animation_res.map[&source_id].clone();
+            clip.flip_x ^= flip_x;
+            animation_res.add(id, clip);
+
+            report.push(format!(
+                "{:?}: from {:?}{}",
+                id,
+                source_id,
+                if flip_x { ", mirrored" } else { "" }
+            ));
         }
+    }
 
-        // Subtract total frames from frame_time as to not accumulate in frame_time.
-        player.frame_time -= animation.frame_time * frames_elapsed as f32;
+    if !report.is_empty() {
+        report.sort();
+        info!("Synthesized player animations:\n  {}", report.join("\n  "));
     }
 }
 
+// Number of frames a clip plays, the declared count if there is one. Fewer than the sheet has
+// plays the first few, more would run off the end of the sheet so the clip is rejected.
+fn frame_count(declared: Option<usize>, sheet_frames: usize) -> Result<usize, String> {
+    match declared {
+        Some(frames) if frames > sheet_frames => {
+            Err(format!("declares {} frames, sheet only has {}", frames, sheet_frames))
+        }
+        Some(frames) => Ok(frames),
+        None => Ok(sheet_frames),
+    }
+}
+
+// Fill in animation parameters from player's state.
+fn update_player_params(
+    mut player_q: Query<(
+        &mut AnimationParams,
+        &Stamina,
+        &Dash,
+        &PlatformerBody,
+        Option<&Dead>,
+    )>,
+    mode: Res<ControlMode>,
+) {
+    for (mut params, stamina, dash, body, dead) in player_q.iter_mut() {
+        params.sprinting = stamina.sprinting;
+        params.dashing = dash.is_dashing();
+        params.dead = dead.is_some();
+        params.grounded = *mode == ControlMode::TopDown || body.grounded;
+    }
+}
+
+// Step player's animation state machine, and play clip for resulting state and direction.
 fn update_player_animation(
-    mut player_q: Query<&mut Player>,
-    item_q: Query<&mut Item>,
-    keyboard_input: Res<Input<KeyCode>>,
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-    mut texture_atlas_query: Query<&mut Handle<TextureAtlas>, With<Player>>,
+    mut player_q: Query<(&mut Player, &mut SpriteAnimator, &AnimationParams)>,
     animation_res: Res<PlayerAnimations>,
 ) {
-    let mut player = player_q.single_mut();
-    let mut atlas = texture_atlas_query.single_mut();
+    let Ok((mut player, mut animator, params)) = player_q.get_single_mut() else {
+        return;
+    };
 
-    let animation_id = if keyboard_input.any_pressed([KeyCode::W, KeyCode::D, KeyCode::S, KeyCode::A, KeyCode::Up, KeyCode::Right, KeyCode::Left, KeyCode::Down])
-        && keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
-            PlayerAnimationType::Run(player.direction.clone())
-        }
-    else if keyboard_input.any_pressed([KeyCode::W, KeyCode::D, KeyCode::S, KeyCode::A, KeyCode::Up, KeyCode::Right, KeyCode::Left, KeyCode::Down]) {
-        PlayerAnimationType::Walk(player.direction.clone())
-    } else {
-        PlayerAnimationType::Idle(player.direction.clone())
+    let current = player.animation.state();
+    let direction = player.direction;
+    let has_clip = |state| animation_res.get(PlayerAnimationType::new(state, direction)).is_some();
+    let next = animation_res.state_machine.next(current, params, &animator, has_clip);
+    let (state, entry_frame) = match next {
+        Some(transition) => (transition.to, Some(transition.entry_frame)),
+        None => (current, None),
     };
 
-    // Get relevant animation and set path accordingly.
-    let Some(new_animation) = animation_res.get(animation_id) else {
+    // Get relevant animation.
+    let animation_id = PlayerAnimationType::new(state, player.direction);
+    let Some(clip) = animation_res.get(animation_id) else {
         return;
     };
 
-    let path = format!("{}.png", &new_animation.path);
-
-    // Load player spritesheet according to relevant path, and splice into single frames. (Why is this so tedious in Bevy?)
-    let texture_handle = asset_server.load(path);
-    let texture_atlas = TextureAtlas::from_grid(texture_handle, Vec2::new(500., 500.), 6, 1, None, None);
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
+    // Entering a new state restarts from its entry frame, turning keeps the current frame.
+    match entry_frame {
+        Some(frame) => animator.play_from(clip, frame),
+        None => animator.switch(clip),
+    }
 
-    // Set player's spritesheet to relevant data.
-    if *atlas != texture_atlas_handle {
-        *atlas = texture_atlas_handle
+    if player.animation != animation_id {
+        player.animation = animation_id;
     }
 }
 
-// Animate idle item on floor.
-fn animate_item_idle(
-    mut item_q: Query<&mut Transform, With<Item>>,
-    mut frame_time: Local<i32>,
-    mut switch: Local<i32>,
-) {
-    const ANIM_LIMIT: i32 = 20; // Limit for top of animation.
-    const STEP: f32 = 0.2; // How much to increase position on each frame.
-
-    if *frame_time < ANIM_LIMIT && *switch == 0 {
-        *frame_time += 1;
-    } else if *frame_time >= ANIM_LIMIT && *switch == 0 {
-        *frame_time = 0;
-        *switch = 1;
-    } else if *frame_time >= -ANIM_LIMIT && *switch == 1 {
-        *frame_time -= 1;
-    } else {
-        *switch = 0;
-        *frame_time = 0;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animator::AnimatorPlugin;
+    use bevy::render::texture::ImageTextureLoader;
+
+    #[test]
+    fn frames_are_packed_into_one_atlas() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(AssetPlugin::default())
+            .add_asset::<Image>()
+            .init_asset_loader::<ImageTextureLoader>()
+            .add_asset::<TextureAtlas>()
+            .init_resource::<ControlMode>()
+            .add_plugins(AnimationPlugin)
+            .add_plugins(AnimatorPlugin);
+
+        let player = app
+            .world
+            .spawn((
+                SpriteSheetBundle::default(),
+                Player {
+                    animation: PlayerAnimationType::Idle(Direction::South),
+                    direction: Direction::South,
+                },
+                SpriteAnimator::default(),
+                AnimationParams::default(),
+                Stamina::new(100.),
+                Dash::default(),
+                PlatformerBody::default(),
+            ))
+            .id();
+
+        // Wait for manifest and spritesheets to load from disk.
+        for _ in 0..1000 {
+            app.update();
+            if !app.world.resource::<PlayerAnimations>().map.is_empty() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(!app.world.resource::<PlayerAnimations>().map.is_empty());
+        assert!(!app.world.resource::<PlayerAnimations>().state_machine.transitions.is_empty());
+
+        // Declared frame counts are kept, even where the sheet has more.
+        let animations = app.world.resource::<PlayerAnimations>();
+        let idle = animations.get(PlayerAnimationType::Idle(Direction::SouthWest));
+        assert_eq!(idle.map(|clip| clip.len), Some(3));
+        let idle = animations.get(PlayerAnimationType::Idle(Direction::South));
+        assert_eq!(idle.map(|clip| clip.len), Some(6));
+
+        let atlas_count = app.world.resource::<Assets<TextureAtlas>>().len();
+
+        // Walk, run and stand around in every direction, so every animation gets picked at some point.
+        let directions = Direction::ALL;
+        for frame in 0..200 {
+            if frame % 4 != 0 {
+                app.world.get_mut::<Transform>(player).unwrap().translation.x += 5.;
+            }
+
+            app.world.get_mut::<Player>(player).unwrap().direction = directions[frame % 8];
+            app.world.get_mut::<Stamina>(player).unwrap().sprinting = frame % 3 == 0;
+            app.update();
+        }
+
+        assert_eq!(app.world.resource::<Assets<TextureAtlas>>().len(), atlas_count);
+
+        // Every animation draws from the same atlas, only the index changes.
+        let animations = app.world.resource::<PlayerAnimations>();
+        let current = app.world.get::<Handle<TextureAtlas>>(player).unwrap();
+        assert!(animations.map.values().all(|clip| clip.atlas == *current));
+        assert_eq!(atlas_count, 1);
+
+        // Trimmed frames are smaller than the 500x500 frames they came from.
+        let atlas = app.world.resource::<Assets<TextureAtlas>>().get(current).unwrap();
+        assert!(atlas.textures.iter().all(|rect| rect.width() < 500. && rect.height() < 500.));
     }
 
-    for mut pos in &mut item_q.iter_mut() {
-        if *switch == 0 {
-            pos.translation.y += STEP; // Going up.
-        } else if *switch == 1 {
-            pos.translation.y -= STEP; // Going down.
-        }
+    #[test]
+    fn declaring_more_frames_than_the_sheet_has_is_rejected() {
+        assert_eq!(frame_count(None, 6), Ok(6));
+        assert_eq!(frame_count(Some(6), 6), Ok(6));
+        assert_eq!(frame_count(Some(3), 6), Ok(3));
+        assert_eq!(frame_count(Some(8), 6), Err("declares 8 frames, sheet only has 6".to_string()));
     }
-}
 
-fn animate_item_in_inv(
-    player_q: Query<(&Player, &Transform, &mut TextureAtlasSprite), With<Player>>,
-    mut item_q: Query<(&mut Transform, &Item), Without<Player>>,
-) {
-    const X_OFFSET: f32 = 20.;
-    const Y_OFFSET: f32 = 5.;
+    #[test]
+    fn vectors_snap_to_the_nearest_of_eight_directions() {
+        let at = |degrees: f32| Direction::from_vec2(Vec2::from_angle(degrees.to_radians()));
+
+        // Slices are 45 degrees wide, centered on each direction.
+        assert_eq!(at(0.), Some(Direction::East));
+        assert_eq!(at(22.4), Some(Direction::East));
+        assert_eq!(at(22.6), Some(Direction::NorthEast));
+        assert_eq!(at(-22.4), Some(Direction::East));
+        assert_eq!(at(-22.6), Some(Direction::SouthEast));
+        assert_eq!(at(90.), Some(Direction::North));
+        assert_eq!(at(-90.), Some(Direction::South));
+        assert_eq!(at(-135.), Some(Direction::SouthWest));
+        assert_eq!(at(157.6), Some(Direction::West));
+        assert_eq!(at(-157.6), Some(Direction::West));
+        assert_eq!(at(-157.4), Some(Direction::SouthWest));
+
+        // Length doesn't matter, but there's no direction without one.
+        assert_eq!(Direction::from_vec2(Vec2::new(0.001, 500.)), Some(Direction::North));
+        assert_eq!(Direction::from_vec2(Vec2::ZERO), None);
+    }
 
-    let (player, player_pos, sprite) = player_q.single();
-    let mut index_num: f32 = 0.;
+    #[test]
+    fn fallback_mirrors_then_collapses_then_defaults() {
+        let policy = FallbackPolicy::default();
 
-    for (mut item_pos, item) in item_q.iter_mut() {
-        if item.in_inv {
-            // Offset to render item in player's hands.
-            item_pos.translation.y = player_pos.translation.y - Y_OFFSET;
+        // Only East and South drawn.
+        let has_clip = |direction| matches!(direction, Direction::East | Direction::South);
 
-            // if player.is_facing_right() {
-            //     item_pos.translation.x = player_pos.translation.x + X_OFFSET;
-            // } else {
-            //     item_pos.translation.x = player_pos.translation.x - X_OFFSET;
-            // }
+        assert_eq!(policy.resolve(Direction::West, has_clip), Some((Direction::East, true)));
+        assert_eq!(policy.resolve(Direction::NorthEast, has_clip), Some((Direction::East, false)));
+        assert_eq!(policy.resolve(Direction::SouthWest, has_clip), Some((Direction::East, true)));
+        assert_eq!(policy.resolve(Direction::North, has_clip), Some((Direction::South, false)));
 
-            // if player.animation.path == "player/idle" {}
-        }
+        let no_mirror = FallbackPolicy {
+            mirror: false,
+            ..default()
+        };
+        assert_eq!(no_mirror.resolve(Direction::West, has_clip), Some((Direction::South, false)));
+
+        let nothing = FallbackPolicy {
+            mirror: false,
+            four_directions: false,
+            default_direction: None,
+        };
+        assert_eq!(nothing.resolve(Direction::West, has_clip), None);
     }
 }
@@ -0,0 +1,316 @@
+use crate::animation::AnimationState;
+use crate::animator::SpriteAnimator;
+use crate::player::ControlMode;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+const TELEPORT_DISTANCE: f32 = 200.; // Moving further in one frame is a teleport, like a respawn.
+
+// What an entity is doing right now, for state machine transitions to check against.
+#[derive(Component, Default, Clone, Debug)]
+pub struct AnimationParams {
+    pub velocity: Vec2, // Measured from how far the entity walked or ran, so not falling in side view.
+    pub grounded: bool,
+    pub attacking: bool,
+    pub sprinting: bool,
+    pub dashing: bool,
+    pub dead: bool,
+    last_position: Option<Vec2>,
+}
+
+impl AnimationParams {
+    pub fn speed(&self) -> f32 {
+        self.velocity.length()
+    }
+}
+
+// Work out velocity from movement since last frame, whatever moved the entity. Teleports keep
+// the velocity from before.
+pub fn measure_velocity(
+    mut params_q: Query<(&mut AnimationParams, &Transform)>,
+    mode: Res<ControlMode>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+
+    for (mut params, pos) in params_q.iter_mut() {
+        let position = pos.translation.truncate();
+
+        if let (Some(last_position), true) = (params.last_position, dt > 0.) {
+            let mut moved = position - last_position;
+
+            // Side view only walks and runs sideways, jumping and falling don't count.
+            if *mode == ControlMode::Platformer {
+                moved.y = 0.;
+            }
+
+            if moved.length() <= TELEPORT_DISTANCE {
+                params.velocity = moved / dt;
+            }
+        }
+        params.last_position = Some(position);
+    }
+}
+
+// Match playback rate to movement speed, for clips that scale with it.
+pub fn scale_playback_speed(mut animator_q: Query<(&mut SpriteAnimator, &AnimationParams)>) {
+    for (mut animator, params) in animator_q.iter_mut() {
+        let speed = animator
+            .clip
+            .as_ref()
+            .and_then(|clip| clip.speed_scale)
+            .map_or(1., |scale| scale.rate(params.speed()));
+
+        if animator.speed != speed {
+            animator.speed = speed;
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub enum Condition {
+    SpeedAbove(f32),
+    SpeedBelow(f32),
+    Grounded(bool),
+    Attacking(bool),
+    Sprinting(bool),
+    Dashing(bool),
+    Dead(bool),
+}
+
+impl Condition {
+    pub fn check(&self, params: &AnimationParams) -> bool {
+        match *self {
+            Condition::SpeedAbove(speed) => params.speed() > speed,
+            Condition::SpeedBelow(speed) => params.speed() < speed,
+            Condition::Grounded(grounded) => params.grounded == grounded,
+            Condition::Attacking(attacking) => params.attacking == attacking,
+            Condition::Sprinting(sprinting) => params.sprinting == sprinting,
+            Condition::Dashing(dashing) => params.dashing == dashing,
+            Condition::Dead(dead) => params.dead == dead,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Transition {
+    #[serde(default)]
+    pub from: Vec<AnimationState>, // States this transition leaves, empty for any state.
+    pub to: AnimationState,
+    #[serde(default)]
+    pub when: Vec<Condition>, // All must hold.
+    #[serde(default)]
+    pub exit_frame: Option<usize>, // Wait for current clip to reach this frame before leaving.
+    #[serde(default)]
+    pub entry_frame: usize, // Frame to start new clip on.
+}
+
+impl Transition {
+    fn can_leave(&self, current: AnimationState, animator: &SpriteAnimator) -> bool {
+        if current == self.to || !(self.from.is_empty() || self.from.contains(&current)) {
+            return false;
+        }
+
+        match self.exit_frame {
+            Some(frame) => animator.finished || animator.frame >= frame,
+            None => true,
+        }
+    }
+}
+
+// Transitions are checked in order, and the first one that applies is taken. Transitions into
+// states without a clip are skipped, so a missing clip can't hold the entity in place.
+#[derive(Deserialize, Default, Clone, Debug)]
+pub struct StateMachine {
+    pub transitions: Vec<Transition>,
+}
+
+impl StateMachine {
+    pub fn next(
+        &self,
+        current: AnimationState,
+        params: &AnimationParams,
+        animator: &SpriteAnimator,
+        has_clip: impl Fn(AnimationState) -> bool,
+    ) -> Option<&Transition> {
+        self.transitions.iter().find(|transition| {
+            has_clip(transition.to)
+                && transition.can_leave(current, animator)
+                && transition.when.iter().all(|condition| condition.check(params))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animator::{LoopMode, SpeedScale, SpriteClip};
+    use bevy::asset::HandleId;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    fn clip(len: usize) -> SpriteClip {
+        SpriteClip {
+            atlas: Handle::weak(HandleId::random::<TextureAtlas>()),
+            len,
+            frames: Vec::new(),
+            frame_time: 0.1,
+            loop_mode: LoopMode::Loop,
+            events: Vec::new(),
+            flip_x: false,
+            speed_scale: None,
+            hands: Vec::new(),
+        }
+    }
+
+    fn state_machine() -> StateMachine {
+        ron::from_str(
+            "(transitions: [
+                (to: Death, when: [Dead(true)]),
+                (from: [Idle, Walk], to: Run, when: [Sprinting(true)]),
+                (from: [Idle], to: Walk, when: [SpeedAbove(10.)]),
+                (from: [Walk, Run], to: Idle, when: [SpeedBelow(10.)], exit_frame: Some(3), entry_frame: 2),
+            ])",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn first_matching_transition_wins() {
+        let machine = state_machine();
+        let animator = SpriteAnimator::default();
+        let params = AnimationParams {
+            velocity: Vec2::new(200., 0.),
+            sprinting: true,
+            dead: true,
+            ..default()
+        };
+
+        let next = machine.next(AnimationState::Idle, &params, &animator, |_| true);
+        assert_eq!(next.map(|t| t.to), Some(AnimationState::Death));
+
+        // Never transitions to the state it's already in.
+        assert!(machine.next(AnimationState::Death, &params, &animator, |_| true).is_none());
+    }
+
+    #[test]
+    fn from_limits_source_states() {
+        let machine = state_machine();
+        let animator = SpriteAnimator::default();
+        let params = AnimationParams {
+            velocity: Vec2::new(200., 0.),
+            ..default()
+        };
+
+        let next = machine.next(AnimationState::Idle, &params, &animator, |_| true);
+        assert_eq!(next.map(|t| t.to), Some(AnimationState::Walk));
+        assert!(machine.next(AnimationState::Dash, &params, &animator, |_| true).is_none());
+    }
+
+    #[test]
+    fn exit_frame_delays_transition() {
+        let machine = state_machine();
+        let mut animator = SpriteAnimator::default();
+        animator.play(&clip(6));
+        let params = AnimationParams::default();
+
+        assert!(machine.next(AnimationState::Walk, &params, &animator, |_| true).is_none());
+
+        animator.tick(0.35);
+        let next = machine.next(AnimationState::Walk, &params, &animator, |_| true);
+        assert_eq!(next.map(|t| (t.to, t.entry_frame)), Some((AnimationState::Idle, 2)));
+    }
+
+    #[test]
+    fn switching_clips_keeps_frame_in_range() {
+        let mut animator = SpriteAnimator::default();
+        animator.play(&clip(6));
+        animator.tick(0.55);
+        assert_eq!(animator.frame, 5);
+
+        // Same state, new direction with fewer frames.
+        animator.switch(&clip(3));
+        assert!(animator.frame < 3);
+
+        animator.play_from(&clip(3), 10);
+        assert_eq!(animator.frame, 2);
+    }
+
+    #[test]
+    fn playback_speed_follows_movement_speed() {
+        let mut app = App::new();
+        app.add_systems(Update, scale_playback_speed);
+
+        let mut walk = clip(6);
+        walk.speed_scale = Some(SpeedScale {
+            reference: 250.,
+            min: 0.5,
+            max: 2.,
+        });
+
+        let mut animator = SpriteAnimator::default();
+        animator.play(&walk);
+        let entity = app.world.spawn((animator, AnimationParams::default())).id();
+
+        let speed_at = |app: &mut App, velocity: f32| {
+            let mut params = app.world.get_mut::<AnimationParams>(entity).unwrap();
+            params.velocity = Vec2::new(velocity, 0.);
+            app.update();
+            app.world.get::<SpriteAnimator>(entity).unwrap().speed
+        };
+
+        assert_eq!(speed_at(&mut app, 250.), 1.);
+        assert_eq!(speed_at(&mut app, 375.), 1.5);
+        assert_eq!(speed_at(&mut app, 50.), 0.5); // Clamped, so slow shuffles still animate.
+        assert_eq!(speed_at(&mut app, 5000.), 2.);
+
+        // Clips without scaling always play at normal speed.
+        app.world.get_mut::<SpriteAnimator>(entity).unwrap().play(&clip(3));
+        assert_eq!(speed_at(&mut app, 375.), 1.);
+    }
+
+    #[test]
+    fn transitions_into_states_without_clips_are_skipped() {
+        let machine = state_machine();
+        let animator = SpriteAnimator::default();
+        let params = AnimationParams {
+            velocity: Vec2::new(200., 0.),
+            dead: true,
+            ..default()
+        };
+
+        // No death clip, so carries on as if the death transition wasn't there.
+        let has_clip = |state| state != AnimationState::Death;
+        let next = machine.next(AnimationState::Idle, &params, &animator, has_clip);
+        assert_eq!(next.map(|t| t.to), Some(AnimationState::Walk));
+        assert!(machine.next(AnimationState::Walk, &params, &animator, has_clip).is_none());
+    }
+
+    #[test]
+    fn velocity_ignores_falling_in_side_view_and_teleports() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(0.1)))
+            .insert_resource(ControlMode::Platformer)
+            .add_systems(Update, measure_velocity);
+        let entity = app.world.spawn((AnimationParams::default(), Transform::default())).id();
+        app.update();
+        app.update();
+
+        let speed_after = |app: &mut App, moved: Vec2| {
+            app.world.get_mut::<Transform>(entity).unwrap().translation += moved.extend(0.);
+            app.update();
+            app.world.get::<AnimationParams>(entity).unwrap().speed()
+        };
+
+        // Falling straight down isn't walking.
+        assert_eq!(speed_after(&mut app, Vec2::new(0., -50.)), 0.);
+        assert_eq!(speed_after(&mut app, Vec2::new(25., -50.)), 250.);
+
+        // Respawning somewhere else isn't a burst of running.
+        assert_eq!(speed_after(&mut app, Vec2::new(-1000., 400.)), 250.);
+
+        *app.world.resource_mut::<ControlMode>() = ControlMode::TopDown;
+        assert_eq!(speed_after(&mut app, Vec2::new(0., -20.)), 200.);
+    }
+}
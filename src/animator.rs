@@ -0,0 +1,389 @@
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use serde::Deserialize;
+
+pub struct AnimatorPlugin;
+
+impl Plugin for AnimatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AnimationEvent>()
+            .add_systems(Update, animate_sprites);
+    }
+}
+
+// What happens once a clip reaches its last frame.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LoopMode {
+    #[default]
+    Loop,     // Start over from first frame.
+    Once,     // Hold last frame.
+    PingPong, // Play backwards to first frame, then forwards again.
+}
+
+// A run of frames in a texture atlas, and how to play them.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SpriteClip {
+    pub atlas: Handle<TextureAtlas>,
+    pub len: usize,
+    pub frames: Vec<ClipFrame>, // Where each frame is in a packed atlas, empty if frames start at index 0.
+    pub frame_time: f32,
+    pub loop_mode: LoopMode,
+    pub events: Vec<FrameEvent>,
+    pub flip_x: bool, // Mirror frames horizontally, to reuse a sheet for the opposite direction.
+    pub speed_scale: Option<SpeedScale>, // Keeps feet from sliding at other movement speeds.
+    // Where held items go, from the frame's center in sheet pixels with y up. One entry covers
+    // every frame, empty if the clip has no hands.
+    pub hands: Vec<Vec2>,
+}
+
+// Scales a clip's playback rate by how fast the entity is moving compared to how fast the clip
+// was drawn for.
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct SpeedScale {
+    pub reference: f32, // Movement speed the clip looks right at, in pixels per second.
+    pub min: f32,
+    pub max: f32,
+}
+
+impl SpeedScale {
+    pub fn rate(&self, speed: f32) -> f32 {
+        if self.reference <= 0. {
+            return 1.;
+        }
+
+        (speed / self.reference).clamp(self.min, self.max)
+    }
+}
+
+// Frame of a clip in a packed atlas.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ClipFrame {
+    pub index: usize,
+    pub anchor: Vec2, // Sprite anchor that puts a trimmed frame back where it was in its sheet.
+}
+
+impl SpriteClip {
+    // Atlas index and sprite anchor for a frame of this clip.
+    pub fn frame(&self, frame: usize) -> (usize, Anchor) {
+        match self.frames.get(frame) {
+            Some(packed) => {
+                // Flipping mirrors the sprite around its anchor, so the anchor has to be mirrored too.
+                let sign = if self.flip_x { Vec2::new(-1., 1.) } else { Vec2::ONE };
+                (packed.index, Anchor::Custom(packed.anchor * sign))
+            }
+            None => (frame, Anchor::Center),
+        }
+    }
+
+    // Hand position on a frame, mirrored along with the frame.
+    pub fn hand(&self, frame: usize) -> Option<Vec2> {
+        let hand = self.hands.get(frame).or(self.hands.first())?;
+        let sign = if self.flip_x { Vec2::new(-1., 1.) } else { Vec2::ONE };
+        Some(*hand * sign)
+    }
+}
+
+// Named event fired when a clip reaches a frame, like a footstep or an attack's hitbox turning on.
+#[derive(Clone, PartialEq, Debug)]
+pub struct FrameEvent {
+    pub frame: usize,
+    pub name: String,
+}
+
+// Sent whenever an animator advances onto a frame with events, so audio, particles and combat
+// can sync up with animations.
+#[derive(Event, Clone, Debug)]
+pub struct AnimationEvent {
+    pub entity: Entity,
+    pub name: String,
+    #[allow(dead_code)] // Nothing in the game fires one event name on several frames yet.
+    pub frame: usize, // Which frame fired it, for events sharing a name across frames.
+}
+
+// Plays a clip on any entity with a sprite sheet, whether player, NPC, enemy, item or effect.
+#[derive(Component, Clone, Debug)]
+pub struct SpriteAnimator {
+    pub clip: Option<SpriteClip>,
+    pub elapsed: f32, // Time spent on current frame.
+    pub speed: f32,   // Playback rate, 1 is normal speed.
+    pub frame: usize,
+    pub finished: bool, // Set once a clip that plays once reaches its last frame.
+    reversing: bool,    // Playing backwards, for ping-pong clips.
+}
+
+impl Default for SpriteAnimator {
+    fn default() -> Self {
+        SpriteAnimator {
+            clip: None,
+            elapsed: 0.,
+            speed: 1.,
+            frame: 0,
+            finished: false,
+            reversing: false,
+        }
+    }
+}
+
+impl SpriteAnimator {
+    // Switch to clip, starting from its first frame. Does nothing if clip is already playing.
+    pub fn play(&mut self, clip: &SpriteClip) {
+        if self.is_playing(clip) {
+            return;
+        }
+
+        self.play_from(clip, 0);
+    }
+
+    // Restart with clip from given frame, clamped to clip's last frame.
+    pub fn play_from(&mut self, clip: &SpriteClip, frame: usize) {
+        self.clip = Some(clip.clone());
+        self.elapsed = 0.;
+        self.frame = frame.min(clip.len.saturating_sub(1));
+        self.finished = false;
+        self.reversing = false;
+    }
+
+    // Swap to an equivalent clip, like the same state facing another way, without restarting.
+    // Frame wraps around if new clip is shorter, so it's always valid.
+    pub fn switch(&mut self, clip: &SpriteClip) {
+        if self.is_playing(clip) {
+            return;
+        }
+
+        self.clip = Some(clip.clone());
+        self.frame %= clip.len.max(1);
+    }
+
+    // Compares everything about the clip, so a clip rebuilt by a hot reload replaces the old copy.
+    pub fn is_playing(&self, clip: &SpriteClip) -> bool {
+        self.clip.as_ref() == Some(clip)
+    }
+
+    // Advance by dt seconds, scaled by speed. Returns every frame stepped onto, in order.
+    pub fn tick(&mut self, dt: f32) -> Vec<usize> {
+        let mut frames = Vec::new();
+
+        let Some(clip) = self.clip.as_ref() else {
+            return frames;
+        };
+
+        if clip.len == 0 || clip.frame_time <= 0. {
+            return frames;
+        }
+
+        let (len, frame_time, loop_mode) = (clip.len, clip.frame_time, clip.loop_mode);
+
+        self.elapsed += dt * self.speed;
+
+        while self.elapsed >= frame_time && !self.finished {
+            self.elapsed -= frame_time;
+            self.step(len, loop_mode);
+            frames.push(self.frame);
+        }
+
+        frames
+    }
+
+    fn step(&mut self, len: usize, loop_mode: LoopMode) {
+        match loop_mode {
+            LoopMode::Loop => self.frame = (self.frame + 1) % len,
+            LoopMode::Once => {
+                self.frame = (self.frame + 1).min(len - 1);
+                self.finished = self.frame == len - 1;
+            }
+            LoopMode::PingPong => {
+                if len == 1 {
+                    return;
+                }
+
+                if self.reversing && self.frame == 0 {
+                    self.reversing = false;
+                } else if !self.reversing && self.frame + 1 >= len {
+                    self.reversing = true;
+                }
+
+                if self.reversing {
+                    self.frame -= 1;
+                } else {
+                    self.frame += 1;
+                }
+            }
+        }
+    }
+}
+
+fn animate_sprites(
+    mut animator_q: Query<(
+        Entity,
+        &mut SpriteAnimator,
+        &mut TextureAtlasSprite,
+        &mut Handle<TextureAtlas>,
+    )>,
+    mut animation_events: EventWriter<AnimationEvent>,
+    time: Res<Time>,
+) {
+    for (entity, mut animator, mut sprite, mut atlas) in animator_q.iter_mut() {
+        let frames = animator.tick(time.delta_seconds());
+
+        let Some(clip) = animator.clip.as_ref() else {
+            continue;
+        };
+
+        // Fire events for every frame passed, even ones skipped over in a long frame.
+        for frame in frames {
+            for event in clip.events.iter().filter(|event| event.frame == frame) {
+                animation_events.send(AnimationEvent {
+                    entity,
+                    name: event.name.clone(),
+                    frame,
+                });
+            }
+        }
+
+        if *atlas != clip.atlas {
+            *atlas = clip.atlas.clone();
+        }
+
+        let (index, anchor) = clip.frame(animator.frame);
+
+        if sprite.index != index {
+            sprite.index = index;
+        }
+
+        if sprite.anchor.as_vec() != anchor.as_vec() {
+            sprite.anchor = anchor;
+        }
+
+        if sprite.flip_x != clip.flip_x {
+            sprite.flip_x = clip.flip_x;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn animator(len: usize, loop_mode: LoopMode) -> SpriteAnimator {
+        let mut animator = SpriteAnimator::default();
+        animator.play(&SpriteClip {
+            atlas: Handle::default(),
+            len,
+            frames: Vec::new(),
+            frame_time: 0.1,
+            loop_mode,
+            events: Vec::new(),
+            flip_x: false,
+            speed_scale: None,
+            hands: Vec::new(),
+        });
+        animator
+    }
+
+    // Step one frame at a time, collecting frame indices.
+    fn frames(animator: &mut SpriteAnimator, count: usize) -> Vec<usize> {
+        (0..count)
+            .map(|_| {
+                animator.tick(0.1001);
+                animator.frame
+            })
+            .collect()
+    }
+
+    #[test]
+    fn loop_wraps_around() {
+        let mut animator = animator(3, LoopMode::Loop);
+        assert_eq!(frames(&mut animator, 5), [1, 2, 0, 1, 2]);
+    }
+
+    #[test]
+    fn once_holds_last_frame() {
+        let mut animator = animator(3, LoopMode::Once);
+        assert_eq!(frames(&mut animator, 4), [1, 2, 2, 2]);
+        assert!(animator.finished);
+    }
+
+    #[test]
+    fn ping_pong_bounces() {
+        let mut animator = animator(3, LoopMode::PingPong);
+        assert_eq!(frames(&mut animator, 6), [1, 2, 1, 0, 1, 2]);
+    }
+
+    #[test]
+    fn sends_events_for_every_frame_passed() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins).add_plugins(AnimatorPlugin);
+
+        let footstep = |frame| FrameEvent {
+            frame,
+            name: "footstep".to_string(),
+        };
+        let mut animator = SpriteAnimator::default();
+        animator.play(&SpriteClip {
+            atlas: Handle::default(),
+            len: 6,
+            frames: Vec::new(),
+            frame_time: 0.1,
+            loop_mode: LoopMode::Loop,
+            events: vec![footstep(2), footstep(5)],
+            flip_x: false,
+            speed_scale: None,
+            hands: Vec::new(),
+        });
+        let entity = app.world.spawn((SpriteSheetBundle::default(), animator)).id();
+
+        // Jump straight past frame 2 in one go.
+        app.world.get_mut::<SpriteAnimator>(entity).unwrap().elapsed = 0.3;
+        app.update();
+
+        let events = app.world.resource::<Events<AnimationEvent>>();
+        let sent: Vec<_> = events
+            .iter_current_update_events()
+            .map(|ev| (ev.entity, ev.name.as_str(), ev.frame))
+            .collect();
+        assert_eq!(sent, [(entity, "footstep", 2)]);
+
+        // Round past frame 5 and back to 2 in one update, told apart by frame.
+        app.world.get_mut::<SpriteAnimator>(entity).unwrap().elapsed = 0.65;
+        app.update();
+
+        let events = app.world.resource::<Events<AnimationEvent>>();
+        let frames: Vec<_> = events.iter_current_update_events().map(|ev| ev.frame).collect();
+        assert_eq!(frames, [5, 2]);
+    }
+
+    #[test]
+    fn hands_per_frame_or_for_whole_clip() {
+        let mut clip = animator(3, LoopMode::Loop).clip.unwrap();
+        assert_eq!(clip.hand(0), None);
+
+        clip.hands = vec![Vec2::new(10., -5.)];
+        assert_eq!(clip.hand(2), Some(Vec2::new(10., -5.)));
+
+        clip.hands = vec![Vec2::new(10., -5.), Vec2::new(12., -3.)];
+        clip.flip_x = true;
+        assert_eq!(clip.hand(1), Some(Vec2::new(-12., -3.)));
+    }
+
+    #[test]
+    fn switching_to_a_changed_clip_replaces_it() {
+        let mut animator = animator(4, LoopMode::Loop);
+        frames(&mut animator, 2);
+
+        let mut faster = animator.clip.clone().unwrap();
+        faster.frame_time = 0.05;
+        assert!(!animator.is_playing(&faster));
+
+        animator.switch(&faster);
+        assert_eq!(animator.clip.as_ref().unwrap().frame_time, 0.05);
+        assert_eq!(animator.frame, 2);
+    }
+
+    #[test]
+    fn speed_scales_playback() {
+        let mut animator = animator(6, LoopMode::Loop);
+        animator.speed = 2.;
+        animator.tick(0.25);
+        assert_eq!(animator.frame, 5);
+    }
+}
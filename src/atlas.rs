@@ -0,0 +1,321 @@
+use crate::animator::ClipFrame;
+use bevy::asset::HandleId;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::sprite::TextureAtlasBuilderError;
+use std::collections::HashMap;
+
+const MAX_ATLAS_SIZE: f32 = 4096.; // Biggest packed atlas texture, in pixels along each side.
+
+// How a spritesheet splits up into frames, read left to right, top to bottom.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SheetGrid {
+    pub frame_size: Vec2,
+    pub columns: usize,
+    pub rows: usize,
+}
+
+impl SheetGrid {
+    // Work out grid from sheet's size, and whichever of frame size and frame count is known.
+    // With neither, frames are assumed square and laid out in a single row.
+    pub fn detect(
+        sheet_size: Vec2,
+        frame_size: Option<Vec2>,
+        frames: Option<usize>,
+    ) -> Result<SheetGrid, String> {
+        let frame_size = match (frame_size, frames) {
+            (Some(frame_size), _) => frame_size,
+            (None, Some(frames)) if frames > 0 => {
+                Vec2::new((sheet_size.x / frames as f32).floor(), sheet_size.y)
+            }
+            (None, _) => Vec2::splat(sheet_size.y),
+        };
+
+        if frame_size.x < 1. || frame_size.y < 1. {
+            return Err(format!("{}x{} sheet has no room for frames", sheet_size.x, sheet_size.y));
+        }
+
+        let grid = sheet_size / frame_size;
+        if grid.x < 1. || grid.y < 1. || grid != grid.floor() {
+            return Err(format!(
+                "{}x{} sheet doesn't split evenly into {}x{} frames",
+                sheet_size.x, sheet_size.y, frame_size.x, frame_size.y
+            ));
+        }
+
+        Ok(SheetGrid {
+            frame_size,
+            columns: grid.x as usize,
+            rows: grid.y as usize,
+        })
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    // Copy of a single frame, trimmed of its transparent border, for using a frame on its own.
+    pub fn trimmed_frame(&self, sheet: &Image, frame: usize) -> Image {
+        trim_frame(sheet, self.frame_rect(frame)).0
+    }
+
+    // Pixel bounds of a frame within the sheet, as (x, y, width, height) from the top left.
+    fn frame_rect(&self, frame: usize) -> (usize, usize, usize, usize) {
+        let (width, height) = (self.frame_size.x as usize, self.frame_size.y as usize);
+        (frame % self.columns * width, frame / self.columns * height, width, height)
+    }
+}
+
+// Cuts frames out of any number of spritesheets and packs them into a single atlas, so a whole
+// character draws from one texture and changing animation only changes the sprite index.
+#[derive(Default)]
+pub struct AtlasPacker {
+    frames: HashMap<(HandleId, usize), (Handle<Image>, Vec2)>, // Trimmed frame image and anchor, by sheet and frame.
+}
+
+impl AtlasPacker {
+    // Queue the first len frames of a sheet, trimmed to their visible pixels. Frames already
+    // queued from the same sheet are only packed once.
+    pub fn add_sheet(
+        &mut self,
+        sheet: &Handle<Image>,
+        grid: SheetGrid,
+        len: usize,
+        images: &mut Assets<Image>,
+    ) {
+        let Some(image) = images.get(sheet) else {
+            return;
+        };
+
+        let trimmed: Vec<_> = (0..len)
+            .filter(|frame| !self.frames.contains_key(&(sheet.id(), *frame)))
+            .map(|frame| (frame, trim_frame(image, grid.frame_rect(frame))))
+            .collect();
+
+        for (frame, (frame_image, anchor)) in trimmed {
+            self.frames
+                .insert((sheet.id(), frame), (images.add(frame_image), anchor));
+        }
+    }
+
+    pub fn finish(
+        self,
+        images: &mut Assets<Image>,
+        texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Result<PackedAtlas, TextureAtlasBuilderError> {
+        let mut builder = TextureAtlasBuilder::default().max_size(Vec2::splat(MAX_ATLAS_SIZE));
+
+        for (frame_image, _) in self.frames.values() {
+            if let Some(image) = images.get(frame_image) {
+                builder.add_texture(frame_image.clone(), image);
+            }
+        }
+
+        let result = builder.finish(images);
+
+        // Frames have all been copied into the atlas texture (or failed to), so free them.
+        for (frame_image, _) in self.frames.values() {
+            images.remove(frame_image);
+        }
+
+        let mut atlas = result?;
+
+        let frames = self
+            .frames
+            .iter()
+            .filter_map(|(key, (frame_image, anchor))| {
+                let index = atlas.get_texture_index(frame_image)?;
+                Some((*key, ClipFrame { index, anchor: *anchor }))
+            })
+            .collect();
+
+        atlas.texture_handles = None;
+
+        Ok(PackedAtlas {
+            atlas: texture_atlases.add(atlas),
+            frames,
+        })
+    }
+}
+
+pub struct PackedAtlas {
+    pub atlas: Handle<TextureAtlas>,
+    frames: HashMap<(HandleId, usize), ClipFrame>,
+}
+
+impl PackedAtlas {
+    // Where the first len frames of a sheet ended up, None if any of them weren't packed.
+    pub fn frames(&self, sheet: &Handle<Image>, len: usize) -> Option<Vec<ClipFrame>> {
+        (0..len)
+            .map(|frame| self.frames.get(&(sheet.id(), frame)).copied())
+            .collect()
+    }
+}
+
+// Copy a frame out of a sheet, cropped to the smallest box holding all its visible pixels.
+// Also returns the sprite anchor that keeps the cropped frame where it sat in the full frame.
+fn trim_frame(sheet: &Image, (x, y, width, height): (usize, usize, usize, usize)) -> (Image, Vec2) {
+    let converted;
+    let sheet = match sheet.texture_descriptor.format {
+        TextureFormat::Rgba8UnormSrgb | TextureFormat::Rgba8Unorm => sheet,
+        _ => match sheet.convert(TextureFormat::Rgba8UnormSrgb) {
+            Some(image) => {
+                converted = image;
+                &converted
+            }
+            None => sheet,
+        },
+    };
+
+    let sheet_width = sheet.texture_descriptor.size.width as usize;
+    let pixel = |px: usize, py: usize| {
+        let start = ((y + py) * sheet_width + x + px) * 4;
+        &sheet.data[start..start + 4]
+    };
+
+    // Bounds of visible pixels, as min inclusive and max exclusive.
+    let mut min = (width, height);
+    let mut max = (0, 0);
+    for py in 0..height {
+        for px in 0..width {
+            if pixel(px, py)[3] > 0 {
+                min = (min.0.min(px), min.1.min(py));
+                max = (max.0.max(px + 1), max.1.max(py + 1));
+            }
+        }
+    }
+
+    // Keep a single transparent pixel from the middle of an empty frame.
+    if min.0 >= max.0 {
+        min = (width / 2, height / 2);
+        max = (min.0 + 1, min.1 + 1);
+    }
+
+    let (trimmed_width, trimmed_height) = (max.0 - min.0, max.1 - min.1);
+    let mut data = Vec::with_capacity(trimmed_width * trimmed_height * 4);
+    for py in min.1..max.1 {
+        for px in min.0..max.0 {
+            data.extend_from_slice(pixel(px, py));
+        }
+    }
+
+    let frame = Image::new(
+        Extent3d {
+            width: trimmed_width as u32,
+            height: trimmed_height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        sheet.texture_descriptor.format,
+    );
+
+    // How far trimmed frame's center is from the full frame's center, with y going up.
+    let offset = Vec2::new(
+        (min.0 + max.0) as f32 / 2. - width as f32 / 2.,
+        height as f32 / 2. - (min.1 + max.1) as f32 / 2.,
+    );
+    let anchor = -offset / Vec2::new(trimmed_width as f32, trimmed_height as f32);
+
+    (frame, anchor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sheet_grid_from_frame_size_or_count() {
+        let sheet = Vec2::new(3000., 500.);
+        let six_wide = SheetGrid {
+            frame_size: Vec2::splat(500.),
+            columns: 6,
+            rows: 1,
+        };
+
+        assert_eq!(SheetGrid::detect(sheet, Some(Vec2::splat(500.)), None), Ok(six_wide));
+        assert_eq!(SheetGrid::detect(sheet, None, Some(6)), Ok(six_wide));
+        assert_eq!(SheetGrid::detect(sheet, None, None), Ok(six_wide));
+
+        let grid = SheetGrid::detect(Vec2::new(64., 64.), Some(Vec2::splat(16.)), None).unwrap();
+        assert_eq!((grid.columns, grid.rows, grid.frame_count()), (4, 4, 16));
+
+        // Frames that don't tile the sheet are an error rather than garbage frames.
+        assert!(SheetGrid::detect(sheet, Some(Vec2::splat(400.)), None).is_err());
+        assert!(SheetGrid::detect(sheet, None, Some(7)).is_err());
+        assert!(SheetGrid::detect(sheet, Some(Vec2::new(600., 600.)), None).is_err());
+    }
+
+    #[test]
+    fn trims_transparent_border_and_keeps_position() {
+        // Two 4x4 frames side by side, with a 2x1 opaque block in the top right of frame 1.
+        let (width, height) = (8, 4);
+        let mut data = vec![0; width * height * 4];
+        for x in [6, 7] {
+            data[x * 4..x * 4 + 4].copy_from_slice(&[255, 0, 0, 255]);
+        }
+        let sheet = Image::new(
+            Extent3d {
+                width: width as u32,
+                height: height as u32,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+        );
+        let grid = SheetGrid::detect(sheet.size(), None, Some(2)).unwrap();
+
+        let (frame, anchor) = trim_frame(&sheet, grid.frame_rect(1));
+        assert_eq!(frame.size(), Vec2::new(2., 1.));
+        assert!(frame.data.chunks(4).all(|pixel| pixel == [255, 0, 0, 255]));
+
+        // Trimmed frame's center is 1 right of and 1.5 above the full frame's, so drawing from
+        // this anchor puts it back there.
+        assert_eq!(anchor, Vec2::new(-0.5, -1.5));
+
+        let (empty, _) = trim_frame(&sheet, grid.frame_rect(0));
+        assert_eq!(empty.size(), Vec2::ONE);
+    }
+
+    #[test]
+    fn packs_sheets_into_one_atlas() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(AssetPlugin::default())
+            .add_asset::<Image>()
+            .add_asset::<TextureAtlas>();
+
+        app.world.resource_scope(|world, mut images: Mut<Assets<Image>>| {
+            let mut texture_atlases = world.resource_mut::<Assets<TextureAtlas>>();
+            let sheet = images.add(Image::new(
+                Extent3d {
+                    width: 12,
+                    height: 4,
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                vec![255; 12 * 4 * 4],
+                TextureFormat::Rgba8UnormSrgb,
+            ));
+            let grid = SheetGrid::detect(Vec2::new(12., 4.), None, None).unwrap();
+
+            let mut packer = AtlasPacker::default();
+            packer.add_sheet(&sheet, grid, 3, &mut images);
+            packer.add_sheet(&sheet, grid, 2, &mut images); // Already queued, nothing new to pack.
+            let packed = packer.finish(&mut images, &mut texture_atlases).unwrap();
+
+            let atlas = texture_atlases.get(&packed.atlas).unwrap();
+            assert_eq!(atlas.len(), 3);
+
+            let frames = packed.frames(&sheet, 3).unwrap();
+            let mut indices: Vec<_> = frames.iter().map(|frame| frame.index).collect();
+            indices.sort();
+            assert_eq!(indices, [0, 1, 2]);
+            assert!(packed.frames(&sheet, 4).is_none());
+
+            // Only the sheet and the atlas texture are left.
+            assert_eq!(images.len(), 2);
+        });
+    }
+}
@@ -0,0 +1,425 @@
+use crate::animator::{LoopMode, SpriteAnimator, SpriteClip};
+use crate::collision::{move_and_collide, Collider, Obstacle, Solid};
+use crate::health::{DamageEvent, DamageSource, Health};
+use crate::map::Destructible;
+use crate::tween::{Ease, Lens, Tween, TweenCompleted};
+use bevy::prelude::*;
+
+const THROW_DISTANCE: f32 = 200.; // How far bombs land from where they're thrown.
+const THROW_TIME: f32 = 0.6; // Seconds in the air.
+const THROW_HEIGHT: f32 = 60.; // How high the arc goes, drawn as an upwards offset.
+const BOMB_Z: f32 = 0.95; // Over tiles and rocks, under the player.
+const BOMB_HALF_SIZE: Vec2 = Vec2::new(8., 8.); // Blocked by walls with this size.
+const FRAME_SIZE: Vec2 = Vec2::new(48., 48.);
+const FUSE_FRAMES: usize = 9; // First row of the sheet, fuse burning down.
+const FUSE_FRAME_TIME: f32 = 0.1;
+const EXPLOSION_FRAME: usize = 18; // Big bomb glowing red.
+const EXPLOSION_TIME: f32 = 0.3; // Seconds explosion effect takes to grow to full size.
+
+pub struct BombPlugin;
+
+impl Plugin for BombPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BombSprites>()
+            .add_event::<ExplosionEvent>()
+            .add_systems(Update, add_bomb_sprites)
+            .add_systems(Update, (fly_thrown_bombs, burn_fuses, explode).chain())
+            .add_systems(Update, despawn_explosion_effects);
+    }
+}
+
+// How a bomb goes off.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Explosive {
+    pub fuse: f32, // Seconds from being thrown to going off.
+    pub radius: f32,
+    pub damage: f32, // At the center, falling off to nothing at the edge.
+    pub knockback: f32,
+}
+
+#[derive(Component)]
+pub struct Bomb {
+    pub explosive: Explosive,
+    pub thrower: Entity,
+    fuse: Timer,
+}
+
+// Bomb flying through the air. The arc is only drawn, ground is where it'll land.
+#[derive(Component)]
+struct Thrown {
+    ground: Vec2,
+    velocity: Vec2,
+    elapsed: f32,
+}
+
+#[derive(Resource)]
+struct BombSprites {
+    atlas: Handle<TextureAtlas>,
+    fuse: SpriteClip,
+}
+
+impl FromWorld for BombSprites {
+    fn from_world(world: &mut World) -> Self {
+        let texture = world.resource::<AssetServer>().load("item/weapon/bomb.png");
+        let atlas = TextureAtlas::from_grid(texture, FRAME_SIZE, 10, 6, None, None);
+        let atlas = world.resource_mut::<Assets<TextureAtlas>>().add(atlas);
+
+        BombSprites {
+            atlas: atlas.clone(),
+            fuse: SpriteClip {
+                atlas,
+                len: FUSE_FRAMES,
+                frames: Vec::new(),
+                frame_time: FUSE_FRAME_TIME,
+                loop_mode: LoopMode::Loop,
+                events: Vec::new(),
+                flip_x: false,
+                speed_scale: None,
+                hands: Vec::new(),
+            },
+        }
+    }
+}
+
+// Sent when a bomb goes off.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ExplosionEvent {
+    pub position: Vec2,
+    pub explosive: Explosive,
+    pub source: DamageSource,
+}
+
+// Throw a lit bomb from a position, landing THROW_DISTANCE away in direction.
+pub fn throw_bomb(
+    commands: &mut Commands,
+    thrower: Entity,
+    from: Vec2,
+    direction: Vec2,
+    explosive: Explosive,
+) {
+    commands.spawn((
+        SpatialBundle::from_transform(Transform::from_translation(from.extend(BOMB_Z))),
+        Bomb {
+            explosive,
+            thrower,
+            fuse: Timer::from_seconds(explosive.fuse, TimerMode::Once),
+        },
+        Thrown {
+            ground: from,
+            velocity: direction.normalize_or_zero() * THROW_DISTANCE / THROW_TIME,
+            elapsed: 0.,
+        },
+    ));
+}
+
+// Fraction of an explosion's damage and knockback that reaches distance from its center.
+pub fn falloff(distance: f32, radius: f32) -> f32 {
+    if radius <= 0. {
+        return 0.;
+    }
+
+    (1. - distance / radius).clamp(0., 1.)
+}
+
+fn add_bomb_sprites(
+    mut commands: Commands,
+    bomb_q: Query<Entity, Added<Bomb>>,
+    sprites: Res<BombSprites>,
+) {
+    for entity in bomb_q.iter() {
+        let mut animator = SpriteAnimator::default();
+        animator.play(&sprites.fuse);
+
+        commands.entity(entity).insert((
+            sprites.atlas.clone(),
+            TextureAtlasSprite::default(),
+            animator,
+        ));
+    }
+}
+
+// Walls that stop bombs. Bombs fly over one-way platforms.
+type SolidQuery<'w, 's> =
+    Query<'w, 's, (&'static Transform, &'static Collider), (With<Solid>, Without<Thrown>)>;
+
+// Move thrown bombs along their arc until they land. Bombs that hit a wall drop at its foot.
+fn fly_thrown_bombs(
+    mut commands: Commands,
+    mut thrown_q: Query<(Entity, &mut Thrown, &mut Transform)>,
+    solid_q: SolidQuery,
+    time: Res<Time>,
+) {
+    let obstacles: Vec<Obstacle> = solid_q
+        .iter()
+        .map(|(transform, collider)| Obstacle {
+            center: transform.translation.truncate(),
+            half_size: collider.half_size,
+            one_way: false,
+        })
+        .collect();
+
+    for (entity, mut thrown, mut pos) in thrown_q.iter_mut() {
+        // Don't overshoot on the last frame.
+        let dt = time.delta_seconds().min(THROW_TIME - thrown.elapsed).max(0.);
+        thrown.elapsed += time.delta_seconds();
+
+        let result = move_and_collide(thrown.ground, BOMB_HALF_SIZE, thrown.velocity * dt, &obstacles);
+        thrown.ground = result.position;
+        if result.hit_wall || result.hit_floor || result.hit_ceiling {
+            thrown.velocity = Vec2::ZERO;
+        }
+
+        let t = (thrown.elapsed / THROW_TIME).min(1.);
+        let height = 4. * THROW_HEIGHT * t * (1. - t);
+        let position = thrown.ground + Vec2::new(0., height);
+        pos.translation = position.extend(pos.translation.z);
+
+        if t >= 1. {
+            commands.entity(entity).remove::<Thrown>();
+        }
+    }
+}
+
+fn burn_fuses(
+    mut commands: Commands,
+    mut bomb_q: Query<(Entity, &mut Bomb, &Transform)>,
+    mut explosion_events: EventWriter<ExplosionEvent>,
+    time: Res<Time>,
+) {
+    for (entity, mut bomb, pos) in bomb_q.iter_mut() {
+        if !bomb.fuse.tick(time.delta()).just_finished() {
+            continue;
+        }
+
+        explosion_events.send(ExplosionEvent {
+            position: pos.translation.truncate(),
+            explosive: bomb.explosive,
+            source: DamageSource::Entity(bomb.thrower),
+        });
+        commands.entity(entity).despawn();
+    }
+}
+
+#[derive(Component)]
+struct ExplosionEffect;
+
+// Hurt and push away everything with health in range, weaker further out, and blow up breakable tiles.
+fn explode(
+    mut commands: Commands,
+    mut explosion_events: EventReader<ExplosionEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
+    health_q: Query<(Entity, &Transform), With<Health>>,
+    tile_q: Query<(Entity, &Transform), With<Destructible>>,
+    sprites: Res<BombSprites>,
+) {
+    for ev in explosion_events.iter() {
+        let explosive = ev.explosive;
+
+        for (target, pos) in health_q.iter() {
+            let offset = pos.translation.truncate() - ev.position;
+            let strength = falloff(offset.length(), explosive.radius);
+            if strength <= 0. {
+                continue;
+            }
+
+            // Straight up if right on top of the bomb, so there's always some push.
+            let away = offset.try_normalize().unwrap_or(Vec2::Y);
+
+            damage_events.send(DamageEvent {
+                target,
+                amount: explosive.damage * strength,
+                source: ev.source,
+                knockback: away * explosive.knockback * strength,
+            });
+        }
+
+        for (tile, pos) in tile_q.iter() {
+            if pos.translation.truncate().distance(ev.position) <= explosive.radius {
+                commands.entity(tile).despawn();
+            }
+        }
+
+        // Flash that grows to roughly the size of the blast.
+        let full_size = Vec3::splat(explosive.radius / FRAME_SIZE.x * 2.);
+        commands.spawn((
+            SpriteSheetBundle {
+                texture_atlas: sprites.atlas.clone(),
+                sprite: TextureAtlasSprite::new(EXPLOSION_FRAME),
+                transform: Transform::from_translation(ev.position.extend(BOMB_Z)),
+                ..default()
+            },
+            Tween::new(Lens::Scale(Vec3::ONE, full_size), EXPLOSION_TIME).with_ease(Ease::QuadOut),
+            ExplosionEffect,
+        ));
+    }
+}
+
+fn despawn_explosion_effects(
+    mut commands: Commands,
+    effect_q: Query<(), With<ExplosionEffect>>,
+    mut completed_events: EventReader<TweenCompleted>,
+) {
+    for ev in completed_events.iter() {
+        if effect_q.contains(ev.entity) {
+            commands.entity(ev.entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tween::TweenPlugin;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    const FRAME_TIME: f32 = 0.05;
+
+    fn test_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(AssetPlugin::default())
+            .add_asset::<Image>()
+            .add_asset::<TextureAtlas>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+                FRAME_TIME,
+            )))
+            .add_event::<DamageEvent>()
+            .add_plugins(TweenPlugin)
+            .add_plugins(BombPlugin);
+        app.update(); // First update has no time delta.
+        app
+    }
+
+    fn explosive() -> Explosive {
+        Explosive {
+            fuse: 1.,
+            radius: 100.,
+            damage: 40.,
+            knockback: 600.,
+        }
+    }
+
+    fn damage_sent(app: &App) -> Vec<DamageEvent> {
+        let events = app.world.resource::<Events<DamageEvent>>();
+        events.iter_current_update_events().cloned().collect()
+    }
+
+    #[test]
+    fn falloff_is_full_at_center_and_gone_at_radius() {
+        assert_eq!(falloff(0., 100.), 1.);
+        assert_eq!(falloff(25., 100.), 0.75);
+        assert_eq!(falloff(100., 100.), 0.);
+        assert_eq!(falloff(150., 100.), 0.);
+        assert_eq!(falloff(0., 0.), 0.);
+    }
+
+    #[test]
+    fn explosion_damage_and_knockback_fall_off_with_distance() {
+        let mut app = test_app();
+        let thrower = app.world.spawn_empty().id();
+        let spawn_target = |app: &mut App, x: f32| {
+            app.world
+                .spawn((Health::new(100.), Transform::from_xyz(x, 0., 1.)))
+                .id()
+        };
+        let close = spawn_target(&mut app, 25.);
+        let far = spawn_target(&mut app, -75.);
+        let outside = spawn_target(&mut app, 150.);
+
+        app.world.send_event(ExplosionEvent {
+            position: Vec2::ZERO,
+            explosive: explosive(),
+            source: DamageSource::Entity(thrower),
+        });
+        app.update();
+
+        let sent = damage_sent(&app);
+        let hit = |target| sent.iter().find(|ev| ev.target == target);
+        assert!(hit(outside).is_none());
+
+        let close = hit(close).unwrap();
+        assert_eq!(close.amount, 30.);
+        assert_eq!(close.knockback, Vec2::new(450., 0.));
+        assert_eq!(close.source, DamageSource::Entity(thrower));
+
+        // Further away takes less, and gets pushed the other way.
+        let far = hit(far).unwrap();
+        assert_eq!(far.amount, 10.);
+        assert_eq!(far.knockback, Vec2::new(-150., 0.));
+    }
+
+    #[test]
+    fn explosion_destroys_breakable_tiles_in_radius() {
+        let mut app = test_app();
+        let near = app.world.spawn((Destructible, Transform::from_xyz(64., 64., 0.9))).id();
+        let distant = app.world.spawn((Destructible, Transform::from_xyz(128., 0., 0.9))).id();
+        let solid = app.world.spawn(Transform::from_xyz(0., 64., 0.9)).id();
+
+        app.world.send_event(ExplosionEvent {
+            position: Vec2::ZERO,
+            explosive: explosive(),
+            source: DamageSource::Environment,
+        });
+        app.update();
+
+        assert!(app.world.get_entity(near).is_none());
+        assert!(app.world.get_entity(distant).is_some());
+        assert!(app.world.get_entity(solid).is_some());
+    }
+
+    #[test]
+    fn thrown_bomb_lands_then_explodes_when_fuse_runs_out() {
+        let mut app = test_app();
+        let thrower = app.world.spawn_empty().id();
+        app.world.spawn((Health::new(100.), Transform::from_xyz(THROW_DISTANCE, 0., 1.)));
+
+        let mut commands_queue = bevy::ecs::system::CommandQueue::default();
+        let mut commands = Commands::new(&mut commands_queue, &app.world);
+        throw_bomb(&mut commands, thrower, Vec2::ZERO, Vec2::X, explosive());
+        commands_queue.apply(&mut app.world);
+
+        let mut bomb_q = app.world.query_filtered::<&Transform, With<Bomb>>();
+        let mut exploded = None;
+        let mut peak: f32 = 0.;
+        for frame in 1..=40 {
+            app.update();
+
+            if let Some(pos) = bomb_q.iter(&app.world).next() {
+                peak = peak.max(pos.translation.y);
+                if frame as f32 * FRAME_TIME > THROW_TIME + FRAME_TIME {
+                    assert_eq!(pos.translation.truncate(), Vec2::new(THROW_DISTANCE, 0.));
+                }
+            }
+
+            if !damage_sent(&app).is_empty() && exploded.is_none() {
+                exploded = Some(frame);
+            }
+        }
+
+        assert!(peak > THROW_HEIGHT * 0.9); // Went up in an arc on the way.
+        assert!(exploded.is_some_and(|frame| (20..=21).contains(&frame))); // One second fuse.
+        assert_eq!(bomb_q.iter(&app.world).count(), 0);
+    }
+
+    #[test]
+    fn thrown_bomb_stops_at_walls() {
+        let mut app = test_app();
+        let thrower = app.world.spawn_empty().id();
+        app.world.spawn((Solid, Collider::new(Vec2::splat(16.)), Transform::from_xyz(116., 0., 0.9)));
+
+        let mut commands_queue = bevy::ecs::system::CommandQueue::default();
+        let mut commands = Commands::new(&mut commands_queue, &app.world);
+        throw_bomb(&mut commands, thrower, Vec2::ZERO, Vec2::X, explosive());
+        commands_queue.apply(&mut app.world);
+
+        for _ in 0..15 {
+            app.update();
+        }
+
+        // Landed against the near side of the wall instead of flying through.
+        let mut bomb_q = app.world.query_filtered::<&Transform, With<Bomb>>();
+        let pos = bomb_q.single(&app.world);
+        assert_eq!(pos.translation.truncate(), Vec2::new(116. - 16. - BOMB_HALF_SIZE.x, 0.));
+    }
+}
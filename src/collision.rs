@@ -0,0 +1,118 @@
+use crate::player::Player;
+use bevy::prelude::*;
+
+// Axis-aligned box, centered on the entity's translation.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Collider {
+    pub half_size: Vec2,
+}
+
+impl Collider {
+    pub fn new(half_size: Vec2) -> Self {
+        Collider { half_size }
+    }
+}
+
+// Blocks movement from every side.
+#[derive(Component)]
+pub struct Solid;
+
+// Only blocks movement from above, so bodies can jump up through it.
+#[derive(Component)]
+pub struct OneWayPlatform;
+
+// A box that something can collide with, gathered from the world before moving a body.
+#[derive(Clone, Copy, Debug)]
+pub struct Obstacle {
+    pub center: Vec2,
+    pub half_size: Vec2,
+    pub one_way: bool,
+}
+
+// Every solid or one-way tile, for systems that move the player against the level.
+pub type ObstacleQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Transform, &'static Collider, Option<&'static OneWayPlatform>),
+    (Or<(With<Solid>, With<OneWayPlatform>)>, Without<Player>),
+>;
+
+pub fn collect_obstacles(obstacle_q: &ObstacleQuery) -> Vec<Obstacle> {
+    obstacle_q
+        .iter()
+        .map(|(transform, collider, one_way)| Obstacle {
+            center: transform.translation.truncate(),
+            half_size: collider.half_size,
+            one_way: one_way.is_some(),
+        })
+        .collect()
+}
+
+#[derive(Default, Clone, Copy, Debug)]
+pub struct MoveResult {
+    pub position: Vec2,
+    pub hit_wall: bool,
+    pub hit_floor: bool,
+    pub hit_ceiling: bool,
+}
+
+pub fn overlaps(a_center: Vec2, a_half: Vec2, b_center: Vec2, b_half: Vec2) -> bool {
+    (a_center.x - b_center.x).abs() < a_half.x + b_half.x
+        && (a_center.y - b_center.y).abs() < a_half.y + b_half.y
+}
+
+// Move a box by delta, one axis at a time, stopping flush against any obstacle in the way.
+pub fn move_and_collide(
+    position: Vec2,
+    half_size: Vec2,
+    delta: Vec2,
+    obstacles: &[Obstacle],
+) -> MoveResult {
+    let mut result = MoveResult {
+        position,
+        ..default()
+    };
+
+    // Horizontal pass, one-way platforms never block sideways movement.
+    result.position.x += delta.x;
+    for obstacle in obstacles.iter().filter(|o| !o.one_way) {
+        if !overlaps(result.position, half_size, obstacle.center, obstacle.half_size) {
+            continue;
+        }
+
+        let edge = half_size.x + obstacle.half_size.x;
+        result.position.x = if delta.x > 0. {
+            obstacle.center.x - edge
+        } else {
+            obstacle.center.x + edge
+        };
+        result.hit_wall = true;
+    }
+
+    // Vertical pass.
+    let previous_bottom = position.y - half_size.y;
+    result.position.y += delta.y;
+    for obstacle in obstacles {
+        if !overlaps(result.position, half_size, obstacle.center, obstacle.half_size) {
+            continue;
+        }
+
+        let top = obstacle.center.y + obstacle.half_size.y;
+
+        // One-way platforms only catch bodies that were above them and are falling.
+        if obstacle.one_way && (delta.y > 0. || previous_bottom < top) {
+            continue;
+        }
+
+        let edge = half_size.y + obstacle.half_size.y;
+        if delta.y > 0. {
+            result.position.y = obstacle.center.y - edge;
+            result.hit_ceiling = true;
+        } else {
+            result.position.y = obstacle.center.y + edge;
+            result.hit_floor = true;
+        }
+    }
+
+    result
+}
@@ -0,0 +1,109 @@
+use crate::collision::{collect_obstacles, move_and_collide, Collider, ObstacleQuery};
+use crate::health::{Dead, Invulnerable};
+use crate::player::{Controlled, Player};
+use bevy::prelude::*;
+
+const DASH_DISTANCE: f32 = 180.;
+const DASH_TIME: f32 = 0.15; // Seconds it takes to cover DASH_DISTANCE.
+const DASH_COOLDOWN: f32 = 0.8; // Seconds between dashes, counted from the start of the last one.
+const DASH_INVULNERABLE_TIME: f32 = 0.2;
+
+const DASH_KEYS: [KeyCode; 2] = [KeyCode::C, KeyCode::ControlLeft];
+
+pub struct DashPlugin;
+
+impl Plugin for DashPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (start_dash, update_dash).chain());
+    }
+}
+
+#[derive(Component)]
+pub struct Dash {
+    cooldown: Timer,
+    active: Option<Timer>, // Counts down the current dash, None when not dashing.
+    velocity: Vec2,
+}
+
+impl Dash {
+    pub fn is_dashing(&self) -> bool {
+        self.active.is_some()
+    }
+}
+
+impl Default for Dash {
+    fn default() -> Self {
+        let mut cooldown = Timer::from_seconds(DASH_COOLDOWN, TimerMode::Once);
+        cooldown.tick(cooldown.duration()); // Ready from the start.
+
+        Dash {
+            cooldown,
+            active: None,
+            velocity: Vec2::ZERO,
+        }
+    }
+}
+
+fn start_dash(
+    mut commands: Commands,
+    mut player_q: Query<(Entity, &Player, &mut Dash, Option<&mut Invulnerable>), Without<Dead>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    time: Res<Time>,
+) {
+    let Ok((entity, player, mut dash, invulnerable)) = player_q.get_single_mut() else {
+        return;
+    };
+
+    dash.cooldown.tick(time.delta());
+
+    if !keyboard_input.any_just_pressed(DASH_KEYS) || !dash.cooldown.finished() {
+        return;
+    }
+
+    dash.cooldown.reset();
+    dash.active = Some(Timer::from_seconds(DASH_TIME, TimerMode::Once));
+    dash.velocity = player.direction.to_vec2() * DASH_DISTANCE / DASH_TIME;
+
+    // Don't cut short longer invulnerability from a recent hit.
+    match invulnerable {
+        Some(mut invulnerable) => invulnerable.extend(DASH_INVULNERABLE_TIME),
+        None => {
+            commands
+                .entity(entity)
+                .insert(Invulnerable::new(DASH_INVULNERABLE_TIME));
+        }
+    }
+}
+
+// Move dashing player, stopping early against walls.
+fn update_dash(
+    mut player_q: Query<(&mut Dash, &mut Transform, &Collider), Controlled>,
+    obstacle_q: ObstacleQuery,
+    time: Res<Time>,
+) {
+    let Ok((mut dash, mut pos, collider)) = player_q.get_single_mut() else {
+        return;
+    };
+
+    let velocity = dash.velocity;
+    let Some(timer) = dash.active.as_mut() else {
+        return;
+    };
+
+    timer.tick(time.delta());
+    let finished = timer.finished();
+
+    let result = move_and_collide(
+        pos.translation.truncate(),
+        collider.half_size,
+        velocity * time.delta_seconds(),
+        &collect_obstacles(&obstacle_q),
+    );
+
+    pos.translation.x = result.position.x;
+    pos.translation.y = result.position.y;
+
+    if finished || result.hit_wall {
+        dash.active = None;
+    }
+}
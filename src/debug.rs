@@ -8,7 +8,7 @@ impl Plugin for DebugPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<FrameRate>()
             .add_plugins(LogDiagnosticsPlugin::default())
-            .add_plugins(FrameTimeDiagnosticsPlugin::default())
+            .add_plugins(FrameTimeDiagnosticsPlugin)
             .add_systems(Startup, spawn_fps_text)
             .add_systems(Update, update_fps)
             .add_systems(Update, update_fps_text);
@@ -0,0 +1,546 @@
+use crate::collision::{
+    collect_obstacles, move_and_collide, overlaps, Collider, ObstacleQuery, OneWayPlatform, Solid,
+};
+use crate::map::PlayerSpawn;
+use crate::platformer::PlatformerBody;
+use crate::player::Player;
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+const INVULNERABLE_TIME: f32 = 1.; // Seconds of invulnerability after taking a hit.
+const FLASH_INTERVAL: f32 = 0.1; // Seconds between sprite visibility toggles while invulnerable.
+const KNOCKBACK_TIME: f32 = 0.15; // Seconds knockback takes to wear off.
+const RESPAWN_DELAY: f32 = 1.5; // Seconds spent dead before respawning.
+
+pub struct HealthPlugin;
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LastCheckpoint>()
+            .add_event::<DamageEvent>()
+            .add_event::<DeathEvent>()
+            .add_systems(Startup, init_checkpoint)
+            .add_systems(Update, (hurt_on_hazards, apply_damage, respawn).chain())
+            .add_systems(Update, log_deaths)
+            .add_systems(Update, update_invulnerability)
+            .add_systems(Update, apply_knockback)
+            .add_systems(Update, reach_checkpoint);
+    }
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Health { current: max, max }
+    }
+
+    pub fn heal(&mut self, amount: f32) {
+        self.current = (self.current + amount).min(self.max);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DamageSource {
+    Entity(Entity),
+    Environment,
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: f32,
+    pub source: DamageSource,
+    pub knockback: Vec2, // Velocity pushed onto target, decays over KNOCKBACK_TIME.
+}
+
+// Sent once when an entity's health reaches zero.
+#[derive(Event, Clone, Debug)]
+pub struct DeathEvent {
+    pub entity: Entity,
+    pub source: DamageSource,
+}
+
+// Ignores damage and flashes the sprite until the timer runs out.
+#[derive(Component)]
+pub struct Invulnerable {
+    pub timer: Timer,
+    flash_timer: Timer,
+}
+
+impl Invulnerable {
+    pub fn new(duration: f32) -> Self {
+        Invulnerable {
+            timer: Timer::from_seconds(duration, TimerMode::Once),
+            flash_timer: Timer::from_seconds(FLASH_INTERVAL, TimerMode::Repeating),
+        }
+    }
+    // Make sure invulnerability lasts at least duration from now, without restarting the flash.
+    pub fn extend(&mut self, duration: f32) {
+        if self.timer.remaining_secs() < duration {
+            self.timer = Timer::from_seconds(duration, TimerMode::Once);
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Knockback {
+    pub velocity: Vec2,
+    timer: Timer,
+}
+
+// Entity has run out of health, and is waiting to respawn.
+#[derive(Component)]
+pub struct Dead {
+    pub timer: Timer,
+}
+
+// Level tile that hurts whoever touches it, like spikes.
+#[derive(Component)]
+pub struct Hazard {
+    pub damage: f32,
+}
+
+// Touching a checkpoint makes it the player's respawn point.
+#[derive(Component)]
+pub struct Checkpoint;
+
+#[derive(Resource, Default)]
+pub struct LastCheckpoint(pub Vec3);
+
+fn init_checkpoint(spawn: Res<PlayerSpawn>, mut checkpoint: ResMut<LastCheckpoint>) {
+    checkpoint.0 = spawn.0;
+}
+
+fn apply_damage(
+    mut commands: Commands,
+    mut health_q: Query<&mut Health, (Without<Invulnerable>, Without<Dead>)>,
+    mut damage_events: EventReader<DamageEvent>,
+    mut death_events: EventWriter<DeathEvent>,
+) {
+    // Invulnerability is only inserted once commands apply, so only the strongest hit on each
+    // target lands this frame, whatever order the hits came in.
+    let mut strongest: HashMap<Entity, &DamageEvent> = HashMap::new();
+    for ev in damage_events.iter() {
+        let hit = strongest.entry(ev.target).or_insert(ev);
+        if ev.amount > hit.amount {
+            *hit = ev;
+        }
+    }
+
+    for ev in strongest.into_values() {
+        let Ok(mut health) = health_q.get_mut(ev.target) else {
+            continue;
+        };
+
+        if health.current <= 0. {
+            continue;
+        }
+
+        health.current = (health.current - ev.amount).max(0.);
+
+        let mut target = commands.entity(ev.target);
+
+        if ev.knockback != Vec2::ZERO {
+            target.insert(Knockback {
+                velocity: ev.knockback,
+                timer: Timer::from_seconds(KNOCKBACK_TIME, TimerMode::Once),
+            });
+        }
+
+        if health.current <= 0. {
+            target.insert(Dead {
+                timer: Timer::from_seconds(RESPAWN_DELAY, TimerMode::Once),
+            });
+            death_events.send(DeathEvent {
+                entity: ev.target,
+                source: ev.source,
+            });
+        } else {
+            target.insert(Invulnerable::new(INVULNERABLE_TIME));
+        }
+    }
+}
+
+// Flash sprite while invulnerable, and make sure it's visible again afterwards.
+fn update_invulnerability(
+    mut commands: Commands,
+    mut invulnerable_q: Query<(Entity, &mut Invulnerable, Option<&mut Visibility>)>,
+    time: Res<Time>,
+) {
+    for (entity, mut invulnerable, visibility) in invulnerable_q.iter_mut() {
+        invulnerable.timer.tick(time.delta());
+        invulnerable.flash_timer.tick(time.delta());
+
+        let finished = invulnerable.timer.finished();
+
+        if let Some(mut visibility) = visibility {
+            if finished {
+                *visibility = Visibility::Inherited;
+            } else if invulnerable.flash_timer.just_finished() {
+                *visibility = match *visibility {
+                    Visibility::Hidden => Visibility::Inherited,
+                    _ => Visibility::Hidden,
+                };
+            }
+        }
+
+        if finished {
+            commands.entity(entity).remove::<Invulnerable>();
+        }
+    }
+}
+
+type KnockbackQuery<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static mut Knockback, &'static mut Transform, Option<&'static Collider>),
+    (Without<Solid>, Without<OneWayPlatform>),
+>;
+
+// Push knocked back entities, stopping against walls if they have a collider.
+fn apply_knockback(
+    mut commands: Commands,
+    mut knockback_q: KnockbackQuery,
+    obstacle_q: ObstacleQuery,
+    time: Res<Time>,
+) {
+    let obstacles = collect_obstacles(&obstacle_q);
+
+    for (entity, mut knockback, mut pos, collider) in knockback_q.iter_mut() {
+        knockback.timer.tick(time.delta());
+
+        // Ease out, strongest right after the hit.
+        let strength = knockback.timer.percent_left();
+        let delta = knockback.velocity * strength * time.delta_seconds();
+        let position = pos.translation.truncate();
+        let position = match collider {
+            Some(collider) => {
+                move_and_collide(position, collider.half_size, delta, &obstacles).position
+            }
+            None => position + delta,
+        };
+        pos.translation = position.extend(pos.translation.z);
+
+        if knockback.timer.finished() {
+            commands.entity(entity).remove::<Knockback>();
+        }
+    }
+}
+
+fn hurt_on_hazards(
+    player_q: Query<(Entity, &Transform, &Collider), With<Player>>,
+    hazard_q: Query<(&Hazard, &Transform, &Collider)>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    let Ok((player, player_pos, player_collider)) = player_q.get_single() else {
+        return;
+    };
+
+    for (hazard, hazard_pos, hazard_collider) in hazard_q.iter() {
+        if overlaps(
+            player_pos.translation.truncate(),
+            player_collider.half_size,
+            hazard_pos.translation.truncate(),
+            hazard_collider.half_size,
+        ) {
+            damage_events.send(DamageEvent {
+                target: player,
+                amount: hazard.damage,
+                source: DamageSource::Environment,
+                knockback: Vec2::ZERO,
+            });
+        }
+    }
+}
+
+fn log_deaths(mut death_events: EventReader<DeathEvent>) {
+    for ev in death_events.iter() {
+        match ev.source {
+            DamageSource::Entity(killer) => info!("{:?} was killed by {:?}.", ev.entity, killer),
+            DamageSource::Environment => info!("{:?} died to the environment.", ev.entity),
+        }
+    }
+}
+
+fn reach_checkpoint(
+    player_q: Query<(&Transform, &Collider), With<Player>>,
+    checkpoint_q: Query<(&Transform, &Collider), With<Checkpoint>>,
+    mut last_checkpoint: ResMut<LastCheckpoint>,
+) {
+    let Ok((player_pos, player_collider)) = player_q.get_single() else {
+        return;
+    };
+
+    for (checkpoint_pos, checkpoint_collider) in checkpoint_q.iter() {
+        if overlaps(
+            player_pos.translation.truncate(),
+            player_collider.half_size,
+            checkpoint_pos.translation.truncate(),
+            checkpoint_collider.half_size,
+        ) {
+            last_checkpoint.0 = checkpoint_pos.translation.truncate().extend(player_pos.translation.z);
+        }
+    }
+}
+
+type RespawnQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut Dead,
+        &'static mut Health,
+        &'static mut Transform,
+        Option<&'static mut PlatformerBody>,
+    ),
+    With<Player>,
+>;
+
+// Bring the player back at the last checkpoint once the death timer runs out.
+fn respawn(
+    mut commands: Commands,
+    mut dead_q: RespawnQuery,
+    last_checkpoint: Res<LastCheckpoint>,
+    time: Res<Time>,
+) {
+    for (entity, mut dead, mut health, mut pos, body) in dead_q.iter_mut() {
+        dead.timer.tick(time.delta());
+
+        if !dead.timer.finished() {
+            continue;
+        }
+
+        pos.translation = last_checkpoint.0;
+        health.current = health.max;
+
+        // Don't keep falling as fast as when the player died.
+        if let Some(mut body) = body {
+            body.velocity = Vec2::ZERO;
+        }
+
+        commands
+            .entity(entity)
+            .remove::<(Dead, Knockback)>()
+            .insert(Invulnerable::new(INVULNERABLE_TIME));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::{Direction, PlayerAnimationType};
+    use crate::player::PLAYER_HALF_SIZE;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    const FRAME_TIME: f32 = 0.05;
+
+    fn test_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+                FRAME_TIME,
+            )))
+            .insert_resource(PlayerSpawn(Vec3::new(10., 20., 1.)))
+            .add_plugins(HealthPlugin);
+        app.update();
+        app
+    }
+
+    fn spawn_player(app: &mut App) -> Entity {
+        app.world
+            .spawn((
+                Player {
+                    animation: PlayerAnimationType::Idle(Direction::South),
+                    direction: Direction::South,
+                },
+                Health::new(100.),
+                Collider::new(PLAYER_HALF_SIZE),
+                Transform::from_xyz(0., 0., 1.),
+                Visibility::Inherited,
+            ))
+            .id()
+    }
+
+    fn damage(app: &mut App, target: Entity, amount: f32, knockback: Vec2) {
+        app.world.send_event(DamageEvent {
+            target,
+            amount,
+            source: DamageSource::Environment,
+            knockback,
+        });
+    }
+
+    // Run the app for roughly the given number of seconds.
+    fn run_for(app: &mut App, seconds: f32) {
+        for _ in 0..(seconds / FRAME_TIME).ceil() as usize {
+            app.update();
+        }
+    }
+
+    #[test]
+    fn damage_reduces_health_and_knocks_back() {
+        let mut app = test_app();
+        let player = spawn_player(&mut app);
+
+        damage(&mut app, player, 30., Vec2::new(500., 0.));
+        app.update();
+
+        assert_eq!(app.world.get::<Health>(player).unwrap().current, 70.);
+        assert!(app.world.get::<Invulnerable>(player).is_some());
+
+        run_for(&mut app, KNOCKBACK_TIME + FRAME_TIME);
+
+        assert!(app.world.get::<Transform>(player).unwrap().translation.x > 0.);
+        assert!(app.world.get::<Knockback>(player).is_none());
+    }
+
+    #[test]
+    fn invulnerability_blocks_damage_then_expires() {
+        let mut app = test_app();
+        let player = spawn_player(&mut app);
+
+        damage(&mut app, player, 10., Vec2::ZERO);
+        app.update();
+        damage(&mut app, player, 10., Vec2::ZERO);
+        app.update();
+
+        assert_eq!(app.world.get::<Health>(player).unwrap().current, 90.);
+
+        // Sprite should flash at some point while invulnerable.
+        let mut hidden = false;
+        for _ in 0..5 {
+            app.update();
+            hidden |= app.world.get::<Visibility>(player) == Some(&Visibility::Hidden);
+        }
+        assert!(hidden);
+
+        run_for(&mut app, INVULNERABLE_TIME);
+
+        assert!(app.world.get::<Invulnerable>(player).is_none());
+        assert_eq!(app.world.get::<Visibility>(player), Some(&Visibility::Inherited));
+
+        damage(&mut app, player, 10., Vec2::ZERO);
+        app.update();
+
+        assert_eq!(app.world.get::<Health>(player).unwrap().current, 80.);
+    }
+
+    #[test]
+    fn only_strongest_hit_in_a_frame_lands() {
+        for amounts in [[10., 25.], [25., 10.]] {
+            let mut app = test_app();
+            let player = spawn_player(&mut app);
+
+            for amount in amounts {
+                damage(&mut app, player, amount, Vec2::ZERO);
+            }
+            app.update();
+
+            assert_eq!(app.world.get::<Health>(player).unwrap().current, 75.);
+        }
+    }
+
+    #[test]
+    fn knockback_stops_at_walls() {
+        let mut app = test_app();
+        let player = spawn_player(&mut app);
+        let wall = Transform::from_xyz(100., 0., 0.);
+        app.world.spawn((Solid, Collider::new(Vec2::splat(32.)), wall));
+
+        damage(&mut app, player, 10., Vec2::new(2000., 0.));
+        run_for(&mut app, KNOCKBACK_TIME + FRAME_TIME);
+
+        let x = app.world.get::<Transform>(player).unwrap().translation.x;
+        assert_eq!(x, 100. - 32. - PLAYER_HALF_SIZE.x);
+    }
+
+    #[test]
+    fn hazards_hurt_on_touch() {
+        let mut app = test_app();
+        let player = spawn_player(&mut app);
+
+        app.world.spawn((
+            Hazard { damage: 15. },
+            Collider::new(Vec2::splat(32.)),
+            Transform::from_xyz(20., 0., 0.),
+        ));
+        app.update();
+
+        assert_eq!(app.world.get::<Health>(player).unwrap().current, 85.);
+        assert!(app.world.get::<Invulnerable>(player).is_some());
+    }
+
+    #[test]
+    fn extending_invulnerability_never_shortens_it() {
+        let mut invulnerable = Invulnerable::new(1.);
+        invulnerable.extend(0.2);
+        assert_eq!(invulnerable.timer.remaining_secs(), 1.);
+
+        invulnerable.timer.tick(Duration::from_secs_f32(0.9));
+        invulnerable.extend(0.2);
+        assert_eq!(invulnerable.timer.remaining_secs(), 0.2);
+    }
+
+    #[test]
+    fn lethal_damage_kills_and_sends_death_event() {
+        let mut app = test_app();
+        let player = spawn_player(&mut app);
+
+        damage(&mut app, player, 150., Vec2::ZERO);
+        app.update();
+
+        assert_eq!(app.world.get::<Health>(player).unwrap().current, 0.);
+        assert!(app.world.get::<Dead>(player).is_some());
+
+        let events = app.world.resource::<Events<DeathEvent>>();
+        let deaths: Vec<_> = events.iter_current_update_events().collect();
+        assert_eq!(deaths.len(), 1);
+        assert_eq!(deaths[0].entity, player);
+        assert_eq!(deaths[0].source, DamageSource::Environment);
+    }
+
+    #[test]
+    fn respawns_at_last_checkpoint() {
+        let mut app = test_app();
+        let player = spawn_player(&mut app);
+
+        assert_eq!(app.world.resource::<LastCheckpoint>().0, Vec3::new(10., 20., 1.));
+
+        // Walk onto a checkpoint.
+        app.world.spawn((
+            Checkpoint,
+            Collider::new(Vec2::splat(32.)),
+            Transform::from_xyz(-100., 50., 0.),
+        ));
+        app.world.get_mut::<Transform>(player).unwrap().translation = Vec3::new(-90., 40., 1.);
+        app.update();
+
+        assert_eq!(app.world.resource::<LastCheckpoint>().0, Vec3::new(-100., 50., 1.));
+
+        // Die somewhere else.
+        app.world.get_mut::<Transform>(player).unwrap().translation = Vec3::new(500., 500., 1.);
+        let mut body = PlatformerBody::default();
+        body.velocity = Vec2::new(0., -900.); // Falling fast.
+        app.world.entity_mut(player).insert(body);
+        damage(&mut app, player, 100., Vec2::ZERO);
+        app.update();
+        assert!(app.world.get::<Dead>(player).is_some());
+
+        run_for(&mut app, RESPAWN_DELAY + FRAME_TIME);
+
+        assert!(app.world.get::<Dead>(player).is_none());
+        assert_eq!(app.world.get::<Health>(player).unwrap().current, 100.);
+        assert_eq!(
+            app.world.get::<Transform>(player).unwrap().translation,
+            Vec3::new(-100., 50., 1.)
+        );
+        assert_eq!(app.world.get::<PlatformerBody>(player).unwrap().velocity, Vec2::ZERO);
+        assert!(app.world.get::<Invulnerable>(player).is_some());
+    }
+}
@@ -0,0 +1,384 @@
+use crate::inventory::{Inventory, InventoryChanged};
+use crate::item::Items;
+use crate::player::Player;
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+
+const SLOT_SIZE: f32 = 44.; // Including border.
+const ICON_SIZE: f32 = 32.;
+const SLOT_GAP: f32 = 4.;
+const BORDER_COLOR: Color = Color::rgb(0.6, 0.6, 0.6);
+const SELECTED_BORDER_COLOR: Color = Color::YELLOW;
+const SLOT_KEYS: [KeyCode; 10] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Key0,
+];
+
+pub struct HotbarPlugin;
+
+impl Plugin for HotbarPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (spawn_hotbar, select_slot, update_hotbar).chain());
+    }
+}
+
+// Box showing one inventory slot.
+#[derive(Component)]
+struct HotbarSlot(usize);
+
+#[derive(Component)]
+struct HotbarIcon(usize);
+
+#[derive(Component)]
+struct HotbarCount(usize);
+
+// Name of the selected item and what F does with it, above the hotbar.
+#[derive(Component)]
+struct HotbarName;
+
+// Build a slot for every slot in the player's inventory, once the player exists. Slots start
+// empty and get filled in by update_hotbar.
+fn spawn_hotbar(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    player_q: Query<&Inventory, (With<Player>, Added<Inventory>)>,
+) {
+    let Ok(inventory) = player_q.get_single() else {
+        return;
+    };
+
+    let font = asset_server.load("font/SourceCodePro.ttf");
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.),
+                width: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(SLOT_GAP),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 18.,
+                        ..default()
+                    },
+                ))
+                .insert(HotbarName);
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(SLOT_GAP),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for slot in 0..inventory.len() {
+                        spawn_slot(parent, slot, font.clone());
+                    }
+                });
+        });
+}
+
+fn spawn_slot(parent: &mut ChildBuilder, slot: usize, font: Handle<Font>) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Px(SLOT_SIZE),
+                height: Val::Px(SLOT_SIZE),
+                border: UiRect::all(Val::Px(2.)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            border_color: BorderColor(BORDER_COLOR),
+            background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.5)),
+            ..default()
+        })
+        .insert(HotbarSlot(slot))
+        .with_children(|parent| {
+            parent
+                .spawn(ImageBundle {
+                    style: Style {
+                        width: Val::Px(ICON_SIZE),
+                        height: Val::Px(ICON_SIZE),
+                        ..default()
+                    },
+                    visibility: Visibility::Hidden,
+                    ..default()
+                })
+                .insert(HotbarIcon(slot));
+
+            // Stack count in the bottom right corner, blank for single items.
+            parent
+                .spawn(
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font,
+                            font_size: 14.,
+                            ..default()
+                        },
+                    )
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        right: Val::Px(2.),
+                        bottom: Val::Px(0.),
+                        ..default()
+                    }),
+                )
+                .insert(HotbarCount(slot));
+        });
+}
+
+// Pick a slot with number keys, or step through them with the mouse wheel, wrapping around.
+fn select_slot(
+    mut player_q: Query<&mut Inventory, With<Player>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut wheel_events: EventReader<MouseWheel>,
+) {
+    let scroll: f32 = wheel_events.iter().map(|ev| ev.y).sum();
+
+    let Ok(mut inventory) = player_q.get_single_mut() else {
+        return;
+    };
+
+    let len = inventory.len();
+    if len == 0 {
+        return;
+    }
+
+    let mut slot = inventory.selected();
+
+    if let Some(key) = SLOT_KEYS.iter().position(|key| keyboard_input.just_pressed(*key)) {
+        slot = key;
+    }
+
+    // Scrolling down moves right, like most games.
+    if scroll < 0. {
+        slot = (slot + 1) % len;
+    } else if scroll > 0. {
+        slot = (slot + len - 1) % len;
+    }
+
+    if slot != inventory.selected() && slot < len {
+        let _ = inventory.select(slot);
+    }
+}
+
+// Refresh icons, counts and highlight in place whenever the player's inventory changes.
+fn update_hotbar(
+    player_q: Query<&Inventory, With<Player>>,
+    mut changed_events: EventReader<InventoryChanged>,
+    mut slot_q: Query<(&HotbarSlot, &mut BorderColor)>,
+    mut icon_q: Query<(&HotbarIcon, &mut UiImage, &mut Visibility)>,
+    mut count_q: Query<(&HotbarCount, &mut Text), Without<HotbarName>>,
+    mut name_q: Query<&mut Text, With<HotbarName>>,
+    item_res: Res<Items>,
+) {
+    let changed: Vec<Entity> = changed_events.iter().map(|ev| ev.entity).collect();
+
+    let Some(inventory) = changed.into_iter().find_map(|entity| player_q.get(entity).ok()) else {
+        return;
+    };
+
+    for (slot, mut border) in slot_q.iter_mut() {
+        border.0 = if slot.0 == inventory.selected() {
+            SELECTED_BORDER_COLOR
+        } else {
+            BORDER_COLOR
+        };
+    }
+
+    for (icon, mut image, mut visibility) in icon_q.iter_mut() {
+        match inventory.get(icon.0) {
+            Some(stack) => {
+                image.texture = item_res.get(stack.id).icon.clone();
+                *visibility = Visibility::Inherited;
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+
+    for (count, mut text) in count_q.iter_mut() {
+        text.sections[0].value = match inventory.get(count.0) {
+            Some(stack) if stack.count > 1 => stack.count.to_string(),
+            _ => String::new(),
+        };
+    }
+
+    for mut text in name_q.iter_mut() {
+        text.sections[0].value = inventory
+            .selected_item()
+            .map(|stack| {
+                let item = item_res.get(stack.id);
+                format!("{} (F to {})", item.name, item.category.verb())
+            })
+            .unwrap_or_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::{Direction, PlayerAnimationType};
+    use crate::inventory::InventoryPlugin;
+    use crate::item::{test_items, test_stack};
+    use bevy::input::mouse::MouseScrollUnit;
+
+    fn test_app(inventory: Inventory) -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(AssetPlugin::default())
+            .add_asset::<Image>()
+            .init_resource::<Input<KeyCode>>()
+            .add_event::<MouseWheel>()
+            .insert_resource(test_items().clone())
+            .add_plugins(InventoryPlugin)
+            .add_plugins(HotbarPlugin);
+
+        let player = app
+            .world
+            .spawn((
+                Player {
+                    animation: PlayerAnimationType::Idle(Direction::South),
+                    direction: Direction::South,
+                },
+                inventory,
+            ))
+            .id();
+
+        // Once to spawn the hotbar, once more for it to see the new inventory.
+        app.update();
+        app.update();
+        (app, player)
+    }
+
+    fn selected(app: &App, player: Entity) -> usize {
+        app.world.get::<Inventory>(player).unwrap().selected()
+    }
+
+    fn scroll(app: &mut App, y: f32) {
+        app.world.send_event(MouseWheel {
+            unit: MouseScrollUnit::Line,
+            x: 0.,
+            y,
+            window: Entity::PLACEHOLDER,
+        });
+        app.update();
+    }
+
+    fn press(app: &mut App, key: KeyCode) {
+        let mut input = app.world.resource_mut::<Input<KeyCode>>();
+        input.clear();
+        input.press(key);
+        app.update();
+        app.world.resource_mut::<Input<KeyCode>>().reset_all();
+    }
+
+    #[test]
+    fn number_keys_and_wheel_select_slots() {
+        let (mut app, player) = test_app(Inventory::new(4));
+
+        press(&mut app, KeyCode::Key3);
+        assert_eq!(selected(&app, player), 2);
+
+        // Keys past the end of the hotbar do nothing.
+        press(&mut app, KeyCode::Key9);
+        assert_eq!(selected(&app, player), 2);
+
+        scroll(&mut app, -1.);
+        assert_eq!(selected(&app, player), 3);
+        scroll(&mut app, -1.);
+        assert_eq!(selected(&app, player), 0);
+        scroll(&mut app, 1.);
+        assert_eq!(selected(&app, player), 3);
+    }
+
+    #[test]
+    fn slots_show_icons_counts_and_selection_in_place() {
+        let mut inventory = Inventory::new(3);
+        inventory.add(&mut test_stack("soda", 5), 8).unwrap();
+        inventory.add(&mut test_stack("ice_cream", 1), 4).unwrap();
+        let (mut app, _) = test_app(inventory);
+
+        let mut slot_q = app.world.query::<(Entity, &HotbarSlot)>();
+        let slots: Vec<Entity> = slot_q.iter(&app.world).map(|(entity, _)| entity).collect();
+        assert_eq!(slots.len(), 3);
+
+        let state = |app: &mut App| {
+            let mut slots: Vec<_> = app
+                .world
+                .query::<(&HotbarSlot, &BorderColor)>()
+                .iter(&app.world)
+                .map(|(slot, border)| (slot.0, border.0 == SELECTED_BORDER_COLOR))
+                .collect();
+            slots.sort();
+            let mut counts: Vec<_> = app
+                .world
+                .query::<(&HotbarCount, &Text)>()
+                .iter(&app.world)
+                .map(|(count, text)| (count.0, text.sections[0].value.clone()))
+                .collect();
+            counts.sort();
+            let mut icons: Vec<_> = app
+                .world
+                .query::<(&HotbarIcon, &Visibility)>()
+                .iter(&app.world)
+                .map(|(icon, visibility)| (icon.0, *visibility == Visibility::Inherited))
+                .collect();
+            icons.sort();
+            let name = app
+                .world
+                .query_filtered::<&Text, With<HotbarName>>()
+                .single(&app.world)
+                .sections[0]
+                .value
+                .clone();
+
+            let selected: Vec<bool> = slots.iter().map(|(_, selected)| *selected).collect();
+            let counts: Vec<String> = counts.into_iter().map(|(_, count)| count).collect();
+            let icons: Vec<bool> = icons.into_iter().map(|(_, shown)| shown).collect();
+            (selected, counts, icons, name)
+        };
+
+        assert_eq!(
+            state(&mut app),
+            (
+                vec![true, false, false],
+                vec!["5".to_string(), String::new(), String::new()],
+                vec![true, true, false],
+                "Soda (F to eat)".to_string(),
+            )
+        );
+
+        press(&mut app, KeyCode::Key2);
+        app.update();
+        let (selected, _, _, name) = state(&mut app);
+        assert_eq!(selected, [false, true, false]);
+        assert_eq!(name, "Ice Cream (F to eat)");
+
+        // Updated without respawning anything.
+        let mut slot_q = app.world.query::<(Entity, &HotbarSlot)>();
+        let now: Vec<Entity> = slot_q.iter(&app.world).map(|(entity, _)| entity).collect();
+        assert_eq!(now, slots);
+    }
+}
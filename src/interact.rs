@@ -0,0 +1,258 @@
+use crate::health::Dead;
+use crate::player::Player;
+use crate::tween::{Ease, Lens, Tween};
+use bevy::prelude::*;
+
+const INTERACT_KEY: KeyCode = KeyCode::E;
+const FACING_THRESHOLD: f32 = 0.5; // Cosine of widest angle from facing direction still counted as "in front".
+const TOUCH_DISTANCE: f32 = 24.; // Anything this close counts as in front, whichever way player faces.
+const PROMPT_POP_TIME: f32 = 0.15; // Seconds for prompt to pop up to full size.
+
+pub struct InteractPlugin;
+
+impl Plugin for InteractPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InteractTarget>()
+            .add_event::<InteractEvent>()
+            .add_systems(Startup, spawn_interact_prompt)
+            .add_systems(Update, (find_interact_target, interact, update_interact_prompt).chain());
+    }
+}
+
+// Something the player can press E on, when within radius.
+#[derive(Component, Clone, Debug)]
+pub struct Interactable {
+    pub radius: f32,
+    pub prompt: String,
+}
+
+impl Interactable {
+    pub fn new(radius: f32, prompt: &str) -> Self {
+        Interactable {
+            radius,
+            prompt: prompt.to_string(),
+        }
+    }
+}
+
+// Interactable the player would use if they pressed E right now.
+#[derive(Resource, Default)]
+pub struct InteractTarget(pub Option<Entity>);
+
+// Sent when player interacts with an entity. Items, doors, chests, NPCs, etc. should
+// read these and respond to ones sent to them.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct InteractEvent {
+    pub entity: Entity,
+    pub interactor: Entity,
+}
+
+// Pick nearest interactable within its radius that the player is facing.
+fn find_interact_target(
+    player_q: Query<(&Player, &Transform), Without<Dead>>,
+    interactable_q: Query<(Entity, &Interactable, &GlobalTransform)>,
+    mut target: ResMut<InteractTarget>,
+) {
+    let Ok((player, player_pos)) = player_q.get_single() else {
+        target.0 = None;
+        return;
+    };
+
+    let player_pos = player_pos.translation.truncate();
+    let facing = player.direction.to_vec2();
+
+    let nearest = interactable_q
+        .iter()
+        .filter_map(|(entity, interactable, pos)| {
+            let offset = pos.translation().truncate() - player_pos;
+            let distance = offset.length();
+
+            let in_range = distance <= interactable.radius;
+            let in_front = distance <= TOUCH_DISTANCE
+                || offset.normalize().dot(facing) >= FACING_THRESHOLD;
+
+            (in_range && in_front).then_some((entity, distance))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(entity, _)| entity);
+
+    if target.0 != nearest {
+        target.0 = nearest;
+    }
+}
+
+fn interact(
+    player_q: Query<Entity, With<Player>>,
+    target: Res<InteractTarget>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut interact_events: EventWriter<InteractEvent>,
+) {
+    if !keyboard_input.just_pressed(INTERACT_KEY) {
+        return;
+    }
+
+    let (Ok(player), Some(entity)) = (player_q.get_single(), target.0) else {
+        return;
+    };
+
+    interact_events.send(InteractEvent {
+        entity,
+        interactor: player,
+    });
+}
+
+#[derive(Component)]
+struct InteractPrompt;
+
+// Spawn prompt text along bottom of screen, hidden until there's something to interact with.
+fn spawn_interact_prompt(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(40.),
+                width: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font: asset_server.load("font/SourceCodePro.ttf"),
+                            font_size: 20.,
+                            color: Color::WHITE,
+                        },
+                    ),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                })
+                .insert(InteractPrompt);
+        });
+}
+
+// Show target's prompt text, popping it in for each new target, or hide prompt when there's no target.
+fn update_interact_prompt(
+    mut commands: Commands,
+    target: Res<InteractTarget>,
+    interactable_q: Query<&Interactable>,
+    mut prompt_q: Query<(Entity, &mut Text, &mut Visibility), With<InteractPrompt>>,
+) {
+    if !target.is_changed() {
+        return;
+    }
+
+    let Ok((prompt, mut text, mut visibility)) = prompt_q.get_single_mut() else {
+        return;
+    };
+
+    match target.0.and_then(|entity| interactable_q.get(entity).ok()) {
+        Some(interactable) => {
+            text.sections[0].value = format!("[E] {}", interactable.prompt);
+            *visibility = Visibility::Inherited;
+
+            commands.entity(prompt).insert(
+                Tween::new(Lens::Scale(Vec3::splat(0.6), Vec3::ONE), PROMPT_POP_TIME)
+                    .with_ease(Ease::BackOut),
+            );
+        }
+        None => *visibility = Visibility::Hidden,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::{Direction, PlayerAnimationType};
+    use crate::tween::TweenPlugin;
+
+    fn test_app() -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(AssetPlugin::default())
+            .init_resource::<Input<KeyCode>>()
+            .add_plugins(TweenPlugin)
+            .add_plugins(InteractPlugin);
+
+        let player = app
+            .world
+            .spawn((
+                Player {
+                    animation: PlayerAnimationType::Idle(Direction::East),
+                    direction: Direction::East,
+                },
+                Transform::from_xyz(0., 0., 1.),
+            ))
+            .id();
+        (app, player)
+    }
+
+    fn spawn_interactable(app: &mut App, x: f32, y: f32, radius: f32) -> Entity {
+        app.world
+            .spawn((Interactable::new(radius, "Open"), GlobalTransform::from_xyz(x, y, 0.)))
+            .id()
+    }
+
+    fn target(app: &mut App) -> Option<Entity> {
+        app.update();
+        app.world.resource::<InteractTarget>().0
+    }
+
+    fn prompt(app: &mut App) -> (String, Visibility) {
+        let mut prompt_q = app.world.query_filtered::<(&Text, &Visibility), With<InteractPrompt>>();
+        let (text, visibility) = prompt_q.single(&app.world);
+        (text.sections[0].value.clone(), *visibility)
+    }
+
+    #[test]
+    fn picks_nearest_in_front_within_radius() {
+        let (mut app, _) = test_app();
+        spawn_interactable(&mut app, 60., 0., 100.);
+        spawn_interactable(&mut app, -30., 0., 100.); // Closer, but behind.
+        spawn_interactable(&mut app, 5., 30., 100.); // Too far off to the side.
+        let diagonal = spawn_interactable(&mut app, 35., 35., 100.);
+        assert_eq!(target(&mut app), Some(diagonal));
+
+        // Closest, but out of its own radius.
+        spawn_interactable(&mut app, 30., 0., 20.);
+        assert_eq!(target(&mut app), Some(diagonal));
+
+        let ahead = spawn_interactable(&mut app, 40., 0., 100.);
+        assert_eq!(target(&mut app), Some(ahead));
+    }
+
+    #[test]
+    fn touching_counts_whichever_way_player_faces() {
+        let (mut app, _) = test_app();
+        spawn_interactable(&mut app, 60., 0., 100.);
+        let behind = spawn_interactable(&mut app, -TOUCH_DISTANCE, 0., 100.);
+        assert_eq!(target(&mut app), Some(behind));
+    }
+
+    #[test]
+    fn prompt_shows_for_target_and_interacting_sends_event() {
+        let (mut app, player) = test_app();
+        assert_eq!(target(&mut app), None);
+        assert_eq!(prompt(&mut app).1, Visibility::Hidden);
+
+        let chest = spawn_interactable(&mut app, 40., 0., 100.);
+        assert_eq!(target(&mut app), Some(chest));
+        assert_eq!(prompt(&mut app), ("[E] Open".to_string(), Visibility::Inherited));
+
+        app.world.resource_mut::<Input<KeyCode>>().press(INTERACT_KEY);
+        app.update();
+        let events = app.world.resource::<Events<InteractEvent>>();
+        let sent: Vec<_> =
+            events.iter_current_update_events().map(|ev| (ev.entity, ev.interactor)).collect();
+        assert_eq!(sent, [(chest, player)]);
+
+        // Walking away hides it again.
+        app.world.get_mut::<Transform>(player).unwrap().translation.x = 500.;
+        assert_eq!(target(&mut app), None);
+        assert_eq!(prompt(&mut app).1, Visibility::Hidden);
+    }
+}
@@ -0,0 +1,386 @@
+use crate::item::ItemStack;
+use bevy::prelude::*;
+
+pub struct InventoryPlugin;
+
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<InventoryChanged>()
+            .add_systems(PostUpdate, send_inventory_changes);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InventoryError {
+    Full,
+    NoSuchSlot(usize),
+    EmptySlot(usize),
+    SlotTaken(usize), // Tried to move an item into a slot that already holds one.
+    InvalidCount(u32), // Tried to take none, or more than a stack holds.
+    Mismatch, // Tried to merge stacks of different items.
+}
+
+// Fixed number of slots, each holding a stack of items or nothing, with one slot selected as the
+// item in hand.
+#[derive(Component, Clone, Debug)]
+pub struct Inventory {
+    slots: Vec<Option<ItemStack>>,
+    selected: usize,
+}
+
+impl Inventory {
+    pub fn new(size: usize) -> Self {
+        Inventory {
+            slots: vec![None; size],
+            selected: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    // Slot juggling for inventory UI, which doesn't exist yet.
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(Option::is_none)
+    }
+
+    #[allow(dead_code)]
+    pub fn is_full(&self) -> bool {
+        self.slots.iter().all(Option::is_some)
+    }
+
+    pub fn get(&self, slot: usize) -> Option<&ItemStack> {
+        self.slots.get(slot)?.as_ref()
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selected_item(&self) -> Option<&ItemStack> {
+        self.get(self.selected)
+    }
+
+    pub fn select(&mut self, slot: usize) -> Result<(), InventoryError> {
+        self.check(slot)?;
+        self.selected = slot;
+        Ok(())
+    }
+
+    // Add as much of stack as fits, topping up stacks of the same item before using empty slots,
+    // and the selected slot before any other. Whatever's added is taken out of stack, so if the
+    // inventory fills up stack holds what's left over.
+    pub fn add(&mut self, stack: &mut ItemStack, max_stack: u32) -> Result<(), InventoryError> {
+        let max_stack = max_stack.max(1);
+        let order: Vec<usize> = std::iter::once(self.selected)
+            .chain((0..self.slots.len()).filter(|&slot| slot != self.selected))
+            .collect();
+
+        for &slot in order.iter() {
+            if stack.count == 0 {
+                break;
+            }
+
+            if let Some(held) = self.slots[slot].as_mut().filter(|held| held.id == stack.id) {
+                let moved = stack.count.min(max_stack.saturating_sub(held.count));
+                held.count += moved;
+                stack.count -= moved;
+            }
+        }
+
+        for &slot in order.iter() {
+            if stack.count == 0 {
+                break;
+            }
+
+            if self.slots[slot].is_none() {
+                let moved = stack.count.min(max_stack);
+                self.slots[slot] = Some(ItemStack::new(stack.id, moved));
+                stack.count -= moved;
+            }
+        }
+
+        if stack.count == 0 {
+            Ok(())
+        } else {
+            Err(InventoryError::Full)
+        }
+    }
+
+    // Empty a slot, returning the whole stack.
+    pub fn remove(&mut self, slot: usize) -> Result<ItemStack, InventoryError> {
+        self.check(slot)?;
+        self.slots[slot].take().ok_or(InventoryError::EmptySlot(slot))
+    }
+
+    // Take some items off a stack, emptying the slot if that was all of them.
+    pub fn take(&mut self, slot: usize, count: u32) -> Result<ItemStack, InventoryError> {
+        self.check(slot)?;
+
+        let held = self.slots[slot].as_mut().ok_or(InventoryError::EmptySlot(slot))?;
+        if count == 0 || count > held.count {
+            return Err(InventoryError::InvalidCount(count));
+        }
+
+        held.count -= count;
+        let taken = ItemStack::new(held.id, count);
+
+        if held.count == 0 {
+            self.slots[slot] = None;
+        }
+
+        Ok(taken)
+    }
+
+    // Move some items off a stack into an empty slot.
+    #[allow(dead_code)]
+    pub fn split(&mut self, from: usize, count: u32, to: usize) -> Result<(), InventoryError> {
+        self.check(to)?;
+
+        if from == to || self.slots[to].is_some() {
+            return Err(InventoryError::SlotTaken(to));
+        }
+
+        let taken = self.take(from, count)?;
+        self.slots[to] = Some(taken);
+        Ok(())
+    }
+
+    // Move as many items as fit from one stack onto another of the same item. Returns how many
+    // were moved, leaving any that didn't fit behind.
+    #[allow(dead_code)]
+    pub fn merge(&mut self, from: usize, to: usize, max_stack: u32) -> Result<u32, InventoryError> {
+        self.check(from)?;
+        self.check(to)?;
+
+        if from == to {
+            return Ok(0);
+        }
+
+        let (Some(source), Some(target)) = (&self.slots[from], &self.slots[to]) else {
+            let empty = if self.slots[from].is_none() { from } else { to };
+            return Err(InventoryError::EmptySlot(empty));
+        };
+
+        if source.id != target.id {
+            return Err(InventoryError::Mismatch);
+        }
+
+        let moved = source.count.min(max_stack.saturating_sub(target.count));
+        if moved == 0 {
+            return Ok(0);
+        }
+
+        self.take(from, moved)?;
+        if let Some(target) = self.slots[to].as_mut() {
+            target.count += moved;
+        }
+
+        Ok(moved)
+    }
+
+    // Exchange contents of two slots, either of which can be empty.
+    #[allow(dead_code)]
+    pub fn swap(&mut self, a: usize, b: usize) -> Result<(), InventoryError> {
+        self.check(a)?;
+        self.check(b)?;
+        self.slots.swap(a, b);
+        Ok(())
+    }
+
+    // Move a stack into an empty slot.
+    #[allow(dead_code)]
+    pub fn move_item(&mut self, from: usize, to: usize) -> Result<(), InventoryError> {
+        self.check(from)?;
+        self.check(to)?;
+
+        if self.slots[from].is_none() {
+            return Err(InventoryError::EmptySlot(from));
+        }
+        if from != to && self.slots[to].is_some() {
+            return Err(InventoryError::SlotTaken(to));
+        }
+
+        self.slots.swap(from, to);
+        Ok(())
+    }
+
+    fn check(&self, slot: usize) -> Result<(), InventoryError> {
+        if slot < self.slots.len() {
+            Ok(())
+        } else {
+            Err(InventoryError::NoSuchSlot(slot))
+        }
+    }
+}
+
+// Sent once a frame for each inventory that changed, so UI can update instead of rebuilding.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct InventoryChanged {
+    pub entity: Entity,
+}
+
+fn send_inventory_changes(
+    inventory_q: Query<Entity, Changed<Inventory>>,
+    mut changed_events: EventWriter<InventoryChanged>,
+) {
+    for entity in inventory_q.iter() {
+        changed_events.send(InventoryChanged { entity });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::test_stack;
+
+    fn contents(inventory: &Inventory) -> Vec<Option<ItemStack>> {
+        (0..inventory.len()).map(|slot| inventory.get(slot).copied()).collect()
+    }
+
+    #[test]
+    fn add_fills_selected_then_first_free_slot() {
+        let mut inventory = Inventory::new(3);
+        inventory.select(1).unwrap();
+
+        assert_eq!(inventory.add(&mut test_stack("soda", 1), 1), Ok(()));
+        assert_eq!(inventory.add(&mut test_stack("ice_cream", 1), 1), Ok(()));
+        assert_eq!(inventory.add(&mut test_stack("soda", 1), 1), Ok(()));
+        assert!(inventory.is_full());
+        assert_eq!(inventory.add(&mut test_stack("soda", 1), 1), Err(InventoryError::Full));
+
+        assert_eq!(
+            contents(&inventory),
+            [Some(test_stack("ice_cream", 1)), Some(test_stack("soda", 1)), Some(test_stack("soda", 1))]
+        );
+        assert_eq!(inventory.selected_item(), Some(&test_stack("soda", 1)));
+    }
+
+    #[test]
+    fn add_merges_into_existing_stacks_first() {
+        let mut inventory = Inventory::new(3);
+        inventory.add(&mut test_stack("ice_cream", 1), 4).unwrap();
+        inventory.add(&mut test_stack("soda", 3), 4).unwrap();
+
+        // Tops up the soda stack before starting a new one, even though slot 0 is selected.
+        inventory.add(&mut test_stack("soda", 1), 4).unwrap();
+        assert_eq!(contents(&inventory), [Some(test_stack("ice_cream", 1)), Some(test_stack("soda", 4)), None]);
+    }
+
+    #[test]
+    fn add_overflows_into_new_slots() {
+        let mut inventory = Inventory::new(3);
+        inventory.add(&mut test_stack("soda", 3), 4).unwrap();
+
+        let mut picked = test_stack("soda", 7);
+        inventory.add(&mut picked, 4).unwrap();
+        assert_eq!(picked.count, 0);
+        assert_eq!(
+            contents(&inventory),
+            [Some(test_stack("soda", 4)), Some(test_stack("soda", 4)), Some(test_stack("soda", 2))]
+        );
+
+        // Only part of it fits, the rest is left in the stack.
+        let mut picked = test_stack("soda", 5);
+        assert_eq!(inventory.add(&mut picked, 4), Err(InventoryError::Full));
+        assert_eq!(picked.count, 3);
+        assert_eq!(inventory.get(2), Some(&test_stack("soda", 4)));
+    }
+
+    #[test]
+    fn split_and_merge() {
+        let mut inventory = Inventory::new(3);
+        inventory.add(&mut test_stack("soda", 6), 8).unwrap();
+
+        inventory.split(0, 2, 2).unwrap();
+        assert_eq!(contents(&inventory), [Some(test_stack("soda", 4)), None, Some(test_stack("soda", 2))]);
+
+        assert_eq!(inventory.split(0, 5, 1), Err(InventoryError::InvalidCount(5)));
+        assert_eq!(inventory.split(0, 0, 1), Err(InventoryError::InvalidCount(0)));
+        assert_eq!(inventory.split(0, 1, 2), Err(InventoryError::SlotTaken(2)));
+        assert_eq!(inventory.split(1, 1, 0), Err(InventoryError::SlotTaken(0)));
+
+        // Splitting off the whole stack moves it.
+        inventory.split(2, 2, 1).unwrap();
+        assert_eq!(contents(&inventory), [Some(test_stack("soda", 4)), Some(test_stack("soda", 2)), None]);
+
+        assert_eq!(inventory.merge(1, 0, 8), Ok(2));
+        assert_eq!(contents(&inventory), [Some(test_stack("soda", 6)), None, None]);
+        assert_eq!(inventory.merge(1, 0, 8), Err(InventoryError::EmptySlot(1)));
+    }
+
+    #[test]
+    fn merge_leaves_overflow_and_rejects_other_items() {
+        let mut inventory = Inventory::new(3);
+        inventory.add(&mut test_stack("soda", 3), 4).unwrap();
+        inventory.split(0, 1, 1).unwrap();
+        inventory.add(&mut test_stack("ice_cream", 1), 4).unwrap();
+        assert_eq!(
+            contents(&inventory),
+            [Some(test_stack("soda", 2)), Some(test_stack("soda", 1)), Some(test_stack("ice_cream", 1))]
+        );
+
+        // Merging onto a stack with only so much room leaves the rest where it was.
+        assert_eq!(inventory.merge(1, 0, 2), Ok(0));
+        assert_eq!(inventory.merge(0, 1, 2), Ok(1));
+        assert_eq!(
+            contents(&inventory),
+            [Some(test_stack("soda", 1)), Some(test_stack("soda", 2)), Some(test_stack("ice_cream", 1))]
+        );
+
+        assert_eq!(inventory.merge(0, 2, 4), Err(InventoryError::Mismatch));
+    }
+
+    #[test]
+    fn remove_take_swap_and_move() {
+        let mut inventory = Inventory::new(3);
+        inventory.add(&mut test_stack("soda", 3), 8).unwrap();
+        inventory.add(&mut test_stack("ice_cream", 1), 4).unwrap();
+
+        inventory.swap(0, 2).unwrap();
+        assert_eq!(contents(&inventory), [None, Some(test_stack("ice_cream", 1)), Some(test_stack("soda", 3))]);
+
+        assert_eq!(inventory.move_item(2, 1), Err(InventoryError::SlotTaken(1)));
+        assert_eq!(inventory.move_item(0, 1), Err(InventoryError::EmptySlot(0)));
+        inventory.move_item(2, 0).unwrap();
+        assert_eq!(contents(&inventory), [Some(test_stack("soda", 3)), Some(test_stack("ice_cream", 1)), None]);
+
+        assert_eq!(inventory.take(0, 2), Ok(test_stack("soda", 2)));
+        assert_eq!(inventory.take(0, 1), Ok(test_stack("soda", 1)));
+        assert_eq!(inventory.take(0, 1), Err(InventoryError::EmptySlot(0)));
+
+        assert_eq!(inventory.remove(1), Ok(test_stack("ice_cream", 1)));
+        assert_eq!(inventory.remove(1), Err(InventoryError::EmptySlot(1)));
+        assert!(inventory.is_empty());
+        assert_eq!(inventory.remove(3), Err(InventoryError::NoSuchSlot(3)));
+        assert_eq!(inventory.select(5), Err(InventoryError::NoSuchSlot(5)));
+    }
+
+    #[test]
+    fn changes_send_events() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins).add_plugins(InventoryPlugin);
+        let entity = app.world.spawn(Inventory::new(2)).id();
+
+        let sent = |app: &App| {
+            let events = app.world.resource::<Events<InventoryChanged>>();
+            events.iter_current_update_events().filter(|ev| ev.entity == entity).count()
+        };
+
+        app.update();
+        assert_eq!(sent(&app), 1); // Newly added counts as a change.
+
+        app.update();
+        assert_eq!(sent(&app), 0);
+
+        app.world
+            .get_mut::<Inventory>(entity)
+            .unwrap()
+            .add(&mut test_stack("soda", 1), 8)
+            .unwrap();
+        app.update();
+        assert_eq!(sent(&app), 1);
+    }
+}
@@ -1,203 +1,1319 @@
+use bevy::asset::{AssetLoader, LoadContext, LoadState, LoadedAsset};
 use bevy::prelude::*;
-use std::collections::HashMap;
-use crate::player::Player;
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::transform::TransformSystem;
+use bevy::utils::BoxedFuture;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use crate::animator::{LoopMode, SpriteAnimator};
+use crate::atlas::SheetGrid;
+use crate::bomb::{throw_bomb, Explosive};
+use crate::collision::{overlaps, Collider};
+use crate::health::{Dead, Health};
+use crate::interact::{InteractEvent, Interactable};
+use crate::inventory::Inventory;
+use crate::mouse::AimMode;
+use crate::player::{Controlled, Player};
+use crate::stamina::RestoreStaminaEvent;
+use crate::status::{Stacking, StatusEffect, StatusEffects, StatusKind};
+use crate::tween::{Ease, Lens, Tween, TweenCompleted};
+
+pub const ITEMS_PATH: &str = "item/database.items.ron"; // Item database, relative to the assets folder.
+const BOB_HEIGHT: f32 = 4.; // How far idle items float up and down.
+const BOB_TIME: f32 = 0.35; // Seconds to float from bottom to top.
+const ITEM_HALF_SIZE: Vec2 = Vec2::new(16., 16.); // Walking into this box picks item up.
+const PICKUP_RANGE: f32 = 60.; // How close player has to be to pick item up with E.
+const PICKUP_EFFECT_TIME: f32 = 0.3; // Seconds pickup effect takes to grow and fade out.
+const DROP_DISTANCE: f32 = 24.; // How far in front of player's feet dropped items land.
+const HELD_SCALE: f32 = 1.; // Held items are drawn smaller than ones on the ground.
+const HELD_Z_OFFSET: f32 = 0.01; // How far in front of or behind the player held items are drawn.
+const DROP_IMMUNITY_TIME: f32 = 1.; // Seconds before walking over a dropped item picks it back up.
+const PICKUP_TEXT_RISE: f32 = 32.; // How far "+1 Soda" floats up over the picker before vanishing.
 
 pub struct ItemPlugin;
 
 impl Plugin for ItemPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<Items>()
-            .add_systems(Startup, spawn_idle_item)
-            .add_systems(Startup, spawn_item_ui)
+        app.add_asset::<ItemDatabase>()
+            .init_asset_loader::<ItemDatabaseLoader>()
+            .init_resource::<Items>()
+            .add_event::<PickupEvent>()
+            .add_systems(Update, (build_items, spawn_idle_item).chain())
+            .add_systems(Startup, spawn_held_item)
+            .add_systems(PostUpdate, update_held_item.before(TransformSystem::TransformPropagate))
+            .add_systems(Update, (tick_pickup_immunity, item_pickup, spawn_pickup_text).chain())
+            .add_systems(Update, despawn_pickup_effects)
             .add_systems(Update, drop_item)
-            .add_systems(Update, update_item_ui);
+            .add_systems(Update, use_item);
     }
 }
 
-#[derive(Component, Clone, Debug)]
+// Handle to an item definition, resolved from its id in the item database at load, so lookups
+// are an index instead of a string hash and can't name an item that doesn't exist.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ItemId(u16);
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ItemCategory {
+    Food,
+    Weapon,
+}
+
+impl ItemCategory {
+    // What using an item of this kind does, for hints like "F to eat".
+    pub fn verb(self) -> &'static str {
+        match self {
+            ItemCategory::Food => "eat",
+            ItemCategory::Weapon => "throw",
+        }
+    }
+}
+
+// What happens when an item is used.
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum ItemEffect {
+    Heal(f32),
+    RestoreStamina(f32),
+    SpeedBuff {
+        multiplier: f32,
+        duration: f32, // Seconds it lasts.
+        #[serde(default)]
+        stacking: Stacking, // What eating another does while it's still going.
+    },
+    Explode {
+        fuse: f32, // Seconds from being thrown to going off.
+        radius: f32,
+        damage: f32, // At the center, falling off to nothing at the edge of the radius.
+        knockback: f32,
+    },
+}
+
+// Definition of a kind of item, shared by every stack of it.
+#[derive(Deserialize, Clone, Debug)]
 pub struct Item {
+    pub id: String, // What data files refer to the item by.
     pub name: String,
     pub icon_path: String,
-    pub in_inv: bool,
+    #[serde(default)]
+    pub icon_frame: Option<IconFrame>, // Set when the icon is one frame of a sprite sheet.
+    #[serde(skip)]
+    pub icon: Handle<Image>, // Loaded through the asset server from icon_path.
+    pub category: ItemCategory,
+    pub max_stack: u32, // Most of this item a single inventory slot can hold.
+    #[serde(default)]
+    pub effects: Vec<ItemEffect>,
 }
 
-impl Item {
-    pub fn new(
-        name: String,
-        icon_path: String,
-        in_inv: bool,
-    ) -> Self {
-        Item {
-            name,
-            icon_path,
-            in_inv,
-        }
+// Frame of a sprite sheet to use as an item's icon.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct IconFrame {
+    pub size: (f32, f32),
+    pub index: usize, // Counting left to right, top to bottom.
+}
+
+// Some number of the same item, whether lying on the ground or in an inventory slot.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ItemStack {
+    pub id: ItemId,
+    pub count: u32,
+}
+
+impl ItemStack {
+    pub fn new(id: ItemId, count: u32) -> Self {
+        ItemStack { id, count }
+    }
+}
+
+// Item definitions as written in the item database file, checked and turned into Items once loaded.
+#[derive(Deserialize, TypeUuid, TypePath, Debug)]
+#[uuid = "0c6d1f3e-7a52-4b8e-9f2d-3e5a8c1b4d70"]
+#[serde(transparent)]
+pub struct ItemDatabase(pub Vec<Item>);
+
+#[derive(Default)]
+pub struct ItemDatabaseLoader;
+
+impl AssetLoader for ItemDatabaseLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let database = ron::de::from_bytes::<ItemDatabase>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(database));
+            Ok(())
+        })
     }
 
-    pub fn empty() -> Self {
-        Item {
-            name: "".to_string(),
-            icon_path: "".to_string(),
-            in_inv: false,
-        }
+    fn extensions(&self) -> &[&str] {
+        &["items.ron"]
     }
 }
 
-#[derive(Resource)]
-struct Items {
-    items: HashMap<String, Item>,
+// Every item in the game, built from the item database and rebuilt whenever it changes on disk.
+#[derive(Resource, Clone)]
+pub struct Items {
+    pub database: Handle<ItemDatabase>,
+    items: Vec<Item>,
+    ids: HashMap<String, ItemId>,
+    pending: Option<Vec<Item>>, // Definitions from the database, waiting on their icons to load.
 }
 
 impl Items {
-    pub fn add(&mut self, id: String, item: Item) {
-        self.items.insert(id, item);
-    }
+    // Read item database straight from disk, relative to the assets folder, without icons.
+    #[cfg(test)]
+    pub fn load(path: &str) -> Result<Self, String> {
+        let ron = std::fs::read_to_string(format!("assets/{}", path))
+            .map_err(|err| format!("Failed to read {}: {}", path, err))?;
 
-    pub fn get(&self, id: String) -> Option<Item> {
-        return self.items.get(&id).cloned();
+        Items::parse(&ron)
     }
-}
 
-// init items for global resource
-impl FromWorld for Items {
-    fn from_world(_world: &mut World) -> Self {
+    // Parse item definitions into a fresh set of items.
+    #[cfg(test)]
+    pub fn parse(ron: &str) -> Result<Self, String> {
+        let ItemDatabase(definitions) = ron::from_str(ron).map_err(|err| err.to_string())?;
+
         let mut items = Items {
-            items: HashMap::new(),
+            database: Handle::default(),
+            items: Vec::new(),
+            ids: HashMap::new(),
+            pending: None,
         };
+        items.update(&definitions)?;
+        Ok(items)
+    }
 
-        items.add(
-            "ice_cream".to_string(),
-            Item {
-                name: "Ice Cream".to_string(),
-                icon_path: "item/food/ice_cream.png".to_string(),
-                in_inv: false,
-            },
-        );
+    // Check item definitions, ids are unique and stacks hold something, then take them in. Items
+    // already loaded keep their ids, so stacks in the world stay the same item after a reload.
+    // Nothing changes if there are problems.
+    pub fn update(&mut self, definitions: &[Item]) -> Result<(), String> {
+        let mut seen = HashSet::new();
+        let mut problems = Vec::new();
 
-        items.add(
-            "soda".to_string(),
-            Item {
-                name: "Soda".to_string(),
-                icon_path: "item/food/soda.png".to_string(),
-                in_inv: false,
-            },
-        );
+        for item in definitions {
+            if !seen.insert(&item.id) {
+                problems.push(format!("{}: defined more than once", item.id));
+                continue;
+            }
+            if item.max_stack == 0 {
+                problems.push(format!("{}: max_stack has to be at least 1", item.id));
+            }
+        }
+
+        if !problems.is_empty() {
+            return Err(problems.join("\n"));
+        }
+
+        for item in definitions {
+            match self.ids.get(&item.id) {
+                Some(id) => self.items[id.0 as usize] = item.clone(),
+                None => {
+                    let id = ItemId(self.items.len() as u16);
+                    self.ids.insert(item.id.clone(), id);
+                    self.items.push(item.clone());
+                }
+            }
+        }
 
-        items
+        Ok(())
+    }
+
+    // Look up an item by the id data files use.
+    pub fn id(&self, id: &str) -> Option<ItemId> {
+        self.ids.get(id).copied()
+    }
+
+    // No items until the database has loaded.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    // Start loading icons for new item definitions, which are taken in once they've all loaded.
+    fn load_icons(&mut self, definitions: &[Item], asset_server: &AssetServer) {
+        let pending = definitions
+            .iter()
+            .map(|item| Item {
+                icon: asset_server.load(&item.icon_path),
+                ..item.clone()
+            })
+            .collect();
+
+        self.pending = Some(pending);
+    }
+
+    pub fn get(&self, id: ItemId) -> &Item {
+        &self.items[id.0 as usize]
+    }
+
+    pub fn max_stack(&self, id: ItemId) -> u32 {
+        self.get(id).max_stack
     }
 }
 
-// Spawn idle item that player can pickup.
-fn spawn_idle_item(
-    mut commands: Commands,
+// Start loading the item database, items get filled in once it's loaded.
+impl FromWorld for Items {
+    fn from_world(world: &mut World) -> Self {
+        Items {
+            database: world.resource::<AssetServer>().load(ITEMS_PATH),
+            items: Vec::new(),
+            ids: HashMap::new(),
+            pending: None,
+        }
+    }
+}
+
+// Rebuild items whenever the database loads or changes on disk, once every icon has loaded. A
+// database with problems is reported and ignored, keeping the items from before.
+fn build_items(
+    mut database_events: EventReader<AssetEvent<ItemDatabase>>,
+    databases: Res<Assets<ItemDatabase>>,
     asset_server: Res<AssetServer>,
-    item_res: Res<Items>,
+    mut images: ResMut<Assets<Image>>,
+    mut item_res: ResMut<Items>,
 ) {
+    let changed = database_events.iter().any(|ev| match ev {
+        AssetEvent::Created { handle } | AssetEvent::Modified { handle } => *handle == item_res.database,
+        AssetEvent::Removed { .. } => false,
+    });
+
+    if changed {
+        if let Some(ItemDatabase(definitions)) = databases.get(&item_res.database) {
+            item_res.load_icons(definitions, &asset_server);
+        }
+    }
+
+    let Some(pending) = &item_res.pending else {
+        return;
+    };
+
+    let loading = pending.iter().any(|item| {
+        matches!(
+            asset_server.get_load_state(&item.icon),
+            LoadState::NotLoaded | LoadState::Loading
+        )
+    });
+    if loading {
+        return;
+    }
+
+    let Some(mut definitions) = item_res.pending.take() else {
+        return;
+    };
+
+    let mut items = item_res.clone();
+    let problems: Vec<String> = [
+        cut_icons(&mut definitions, &mut images),
+        items.update(&definitions),
+    ]
+    .into_iter()
+    .filter_map(Result::err)
+    .collect();
+
+    if problems.is_empty() {
+        *item_res = items;
+    } else {
+        error!("Problems with item database {}:\n{}", ITEMS_PATH, problems.join("\n"));
+    }
+}
+
+// Check every icon loaded, and swap sprite sheets for the frame the icon is in.
+fn cut_icons(definitions: &mut [Item], images: &mut Assets<Image>) -> Result<(), String> {
+    let mut problems = Vec::new();
+
+    for item in definitions.iter_mut() {
+        let Some(sheet) = images.get(&item.icon) else {
+            problems.push(format!("{}: no icon at {}", item.id, item.icon_path));
+            continue;
+        };
+
+        let Some(frame) = item.icon_frame else {
+            continue;
+        };
+
+        match cut_icon(sheet, &item.icon_path, frame) {
+            Ok(icon) => item.icon = images.add(icon),
+            Err(err) => problems.push(format!("{}: {}", item.id, err)),
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems.join("\n"))
+    }
+}
+
+// Item database read once and shared by every test, without icons.
+#[cfg(test)]
+pub(crate) fn test_items() -> &'static Items {
+    static ITEMS: std::sync::OnceLock<Items> = std::sync::OnceLock::new();
+    ITEMS.get_or_init(|| Items::load(ITEMS_PATH).unwrap())
+}
+
+#[cfg(test)]
+pub(crate) fn test_stack(id: &str, count: u32) -> ItemStack {
+    ItemStack::new(test_items().id(id).unwrap(), count)
+}
+
+// Copy one frame out of a loaded sprite sheet.
+fn cut_icon(sheet: &Image, path: &str, frame: IconFrame) -> Result<Image, String> {
+    let grid = SheetGrid::detect(sheet.size(), Some(Vec2::new(frame.size.0, frame.size.1)), None)?;
+    if frame.index >= grid.frame_count() {
+        return Err(format!("icon frame {} is past the end of {}", frame.index, path));
+    }
+
+    Ok(grid.trimmed_frame(sheet, frame.index))
+}
+
+// Spawn idle items that player can pickup, once the item database has loaded.
+fn spawn_idle_item(mut commands: Commands, item_res: Res<Items>, mut spawned: Local<bool>) {
+    if *spawned || item_res.is_empty() {
+        return;
+    }
+    *spawned = true;
+
+    let Some(id) = item_res.id("ice_cream") else {
+        return;
+    };
+    let stack = ItemStack::new(id, 1);
+
+    spawn_world_item(&mut commands, &item_res, stack, Vec3::new(100., -200., 0.));
+
+    if let Some(id) = item_res.id("bomb") {
+        spawn_world_item(&mut commands, &item_res, ItemStack::new(id, 3), Vec3::new(300., 0., 0.));
+    }
+}
+
+// Spawn a stack of items lying on the ground, ready to be picked up.
+fn spawn_world_item(
+    commands: &mut Commands,
+    item_res: &Items,
+    stack: ItemStack,
+    pos: Vec3,
+) -> Entity {
     const SCALE: f32 = 2.;
 
-    let Some(ice_cream_item) = item_res.get("ice_cream".to_string()) else { return; };
+    let item = item_res.get(stack.id);
 
     commands.spawn(
         SpriteBundle {
-            texture: asset_server.load(ice_cream_item.icon_path.to_string()),
+            texture: item.icon.clone(),
             transform: Transform {
-                translation: Vec3::new(100., -200., 0.),
+                translation: pos,
                 scale: Vec3::new(SCALE, SCALE, 0.),
                 ..default()
             },
-                ..default()
+            ..default()
         }
     )
-    .insert(ice_cream_item.clone());
+    .insert(Collider::new(ITEM_HALF_SIZE))
+    .insert(Interactable::new(PICKUP_RANGE, &format!("Pick up {}", item.name)))
+    .insert(idle_bob(pos))
+    .insert(stack)
+    .id()
 }
 
-// Check if player has "picked up" (collided with) an item.
-fn item_pickup( ) {
-    todo!();
+// Float up and down in place, out of step with items elsewhere.
+fn idle_bob(pos: Vec3) -> Tween {
+    let phase = (pos.x * 0.37 + pos.y * 0.11).rem_euclid(BOB_TIME * 2.);
+
+    Tween::new(Lens::Translation(pos, pos + Vec3::new(0., BOB_HEIGHT, 0.)), BOB_TIME)
+        .with_ease(Ease::SineInOut)
+        .with_loop_mode(LoopMode::PingPong)
+        .with_phase(phase)
 }
 
-fn drop_item(
-    player_q: Query<&Transform, With<Player>>,
-    mut item_q: Query<&mut Item>,
-    keyboard_input: Res<Input<KeyCode>>
+// Sent when items on the ground are picked up.
+#[derive(Event, Clone, Debug)]
+pub struct PickupEvent {
+    pub picker: Entity,
+    pub stack: ItemStack, // How many were picked up, which might not be all of them.
+}
+
+// Items lying on the ground.
+type WorldItemQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut ItemStack,
+        &'static Transform,
+        &'static Collider,
+        &'static Handle<Image>,
+        Option<&'static PickupImmunity>,
+    ),
+>;
+
+// Anything alive that can carry items. Only the player picks items up by walking into them.
+type PickerQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Transform,
+        &'static Collider,
+        &'static mut Inventory,
+        Option<&'static Player>,
+    ),
+    Without<Dead>,
+>;
+
+// Pick up items the living player walks into, or that anyone alive with an inventory interacts
+// with, as many as there's room for in the picker's inventory.
+fn item_pickup(
+    mut commands: Commands,
+    mut picker_q: PickerQuery,
+    mut item_q: WorldItemQuery,
+    mut interact_events: EventReader<InteractEvent>,
+    mut pickup_events: EventWriter<PickupEvent>,
+    item_res: Res<Items>,
 ) {
-    let pos = player_q.single();
+    // Who picks up what, as (picker, item) pairs.
+    let mut pickups: Vec<(Entity, Entity)> =
+        interact_events.iter().map(|ev| (ev.interactor, ev.entity)).collect();
+
+    for (picker, picker_pos, picker_collider, _, player) in picker_q.iter() {
+        if player.is_none() {
+            continue;
+        }
+
+        for (entity, _, pos, collider, _, immunity) in item_q.iter() {
+            let touching = immunity.is_none() && overlaps(
+                picker_pos.translation.truncate(),
+                picker_collider.half_size,
+                pos.translation.truncate(),
+                collider.half_size,
+            );
+
+            if touching && !pickups.contains(&(picker, entity)) {
+                pickups.push((picker, entity));
+            }
+        }
+    }
+
+    for (picker, entity) in pickups {
+        let (Ok((_, _, _, mut inventory, _)), Ok((_, mut stack, pos, _, texture, _))) =
+            (picker_q.get_mut(picker), item_q.get_mut(entity))
+        else {
+            continue;
+        };
+
+        // Only flag the inventory as changed if something actually went in, so standing on an
+        // item with a full inventory doesn't refresh the hotbar every frame.
+        let count = stack.count;
+        let max_stack = item_res.max_stack(stack.id);
+        let result = inventory.bypass_change_detection().add(&mut stack, max_stack);
+        let picked_up = count - stack.count;
+
+        if picked_up == 0 {
+            continue; // No room, leave it on the ground.
+        }
+
+        inventory.set_changed();
+
+        spawn_pickup_effect(&mut commands, pos, texture.clone());
 
-    for mut item in &mut item_q.iter_mut() {
-        if item.in_inv && keyboard_input.just_pressed(KeyCode::Q) {
-            item.in_inv = false;
+        // Whatever didn't fit stays behind.
+        if result.is_ok() {
+            commands.entity(entity).despawn();
         }
+
+        pickup_events.send(PickupEvent {
+            picker,
+            stack: ItemStack::new(stack.id, picked_up),
+        });
     }
 }
 
 #[derive(Component)]
-struct ItemUI;
+struct PickupEffect;
 
-fn spawn_item_ui(
-    mut commands: Commands,
-) {
-    // Spawn item box outline.
-    commands.spawn(NodeBundle {
-        style: Style {
-            width: Val::Px(30.),
-            height: Val::Px(30.),
-            top: Val::Px(30.),
-            left: Val::Px(30.),
-            border: UiRect::all(Val::Px(2.)),
-            padding: UiRect::all(Val::Px(30.)),
-            ..default()
-        },
-        border_color: BorderColor(Color::WHITE),
-        ..default()
-    });
+// Copy of picked up item's icon that grows and fades out where it was.
+fn spawn_pickup_effect(commands: &mut Commands, pos: &Transform, texture: Handle<Image>) {
+    let mut effect_pos = *pos;
+    effect_pos.translation.z += 1.;
+
+    commands
+        .spawn(SpatialBundle::from_transform(effect_pos))
+        .insert(PickupEffect)
+        .insert(
+            Tween::new(Lens::Scale(pos.scale, pos.scale * 2.), PICKUP_EFFECT_TIME)
+                .with_ease(Ease::QuadOut),
+        )
+        .with_children(|parent| {
+            parent
+                .spawn(SpriteBundle {
+                    texture,
+                    ..default()
+                })
+                .insert(Tween::new(
+                    Lens::Color(Color::WHITE, Color::rgba(1., 1., 1., 0.)),
+                    PICKUP_EFFECT_TIME,
+                ));
+        });
 }
 
-// Spawn and despawn current item in ui.
-fn update_item_ui(
+// Float what was picked up, like "+2 Soda", up from over the picker's head.
+fn spawn_pickup_text(
     mut commands: Commands,
+    mut pickup_events: EventReader<PickupEvent>,
+    picker_q: Query<(&Transform, Option<&Collider>)>,
     asset_server: Res<AssetServer>,
-    item_q: Query<&Item>,
-    item_ui_q: Query<Entity, With<ItemUI>>,
+    item_res: Res<Items>,
 ) {
-    for item in item_q.iter() {
-        if item.in_inv {
-            // Item image.
-            commands.spawn(ImageBundle {
-                image: asset_server.load(item.icon_path.to_string()).into(),
-                transform: Transform {
-                    scale: Vec3::new(2.5, 2.5, 0.),
-                    ..default()
-                },
-                style: Style {
-                    position_type: PositionType::Absolute,
-                    top: Val::Px(45.),
-                    left: Val::Px(45.),
-                    ..default()
-                },
+    for ev in pickup_events.iter() {
+        let Ok((pos, collider)) = picker_q.get(ev.picker) else {
+            continue;
+        };
+
+        let head = collider.map_or(0., |collider| collider.half_size.y);
+        let start = pos.translation + Vec3::new(0., head, 1.);
+
+        commands
+            .spawn(Text2dBundle {
+                text: Text::from_section(
+                    format!("+{} {}", ev.stack.count, item_res.get(ev.stack.id).name),
+                    TextStyle {
+                        font: asset_server.load("font/SourceCodePro.ttf"),
+                        font_size: 16.,
+                        ..default()
+                    },
+                ),
+                transform: Transform::from_translation(start),
                 ..default()
             })
-            .insert(ItemUI);
-
-            let font_handle = asset_server.load("font/SourceCodePro.ttf");
-            // Item name.
-            commands.spawn(TextBundle::from_section(
-                item.name.to_string(),
-                TextStyle {
-                    font: font_handle,
-                    ..default()
-                })
-                .with_style(Style {
-                    position_type: PositionType::Absolute,
-                    bottom: Val::Px(10.),
-                    left: Val::Px(10.),
-                    ..default()
+            .insert(PickupEffect)
+            .insert(
+                Tween::new(
+                    Lens::Translation(start, start + Vec3::new(0., PICKUP_TEXT_RISE, 0.)),
+                    PICKUP_EFFECT_TIME * 2.,
+                )
+                .with_ease(Ease::QuadOut),
+            );
+    }
+}
+
+fn despawn_pickup_effects(
+    mut commands: Commands,
+    mut tween_events: EventReader<TweenCompleted>,
+    effect_q: Query<(), With<PickupEffect>>,
+) {
+    for ev in tween_events.iter() {
+        if effect_q.contains(ev.entity) {
+            commands.entity(ev.entity).despawn_recursive();
+        }
+    }
+}
+
+// Stops a freshly dropped item being picked straight back up by walking over it. Pressing E on it
+// still works.
+#[derive(Component)]
+struct PickupImmunity {
+    timer: Timer,
+}
+
+fn tick_pickup_immunity(
+    mut commands: Commands,
+    mut immunity_q: Query<(Entity, &mut PickupImmunity)>,
+    time: Res<Time>,
+) {
+    for (entity, mut immunity) in immunity_q.iter_mut() {
+        if immunity.timer.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<PickupImmunity>();
+        }
+    }
+}
+
+// Drop the whole selected stack at player's feet, just ahead of them.
+fn drop_item(
+    mut commands: Commands,
+    mut player_q: Query<(&Player, &Transform, &Collider, &mut Inventory), Without<Dead>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    item_res: Res<Items>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Q) {
+        return;
+    }
+
+    let Ok((player, pos, collider, mut inventory)) = player_q.get_single_mut() else {
+        return;
+    };
+
+    let selected = inventory.selected();
+    let Ok(stack) = inventory.remove(selected) else {
+        return;
+    };
+
+    let feet = pos.translation.truncate() - Vec2::new(0., collider.half_size.y);
+    let drop_pos = feet + player.direction.to_vec2() * DROP_DISTANCE;
+    let item = spawn_world_item(&mut commands, &item_res, stack, drop_pos.extend(0.));
+    commands.entity(item).insert(PickupImmunity {
+        timer: Timer::from_seconds(DROP_IMMUNITY_TIME, TimerMode::Once),
+    });
+}
+
+// Players that can use items, with everything item effects can change.
+type UserQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Player,
+        &'static Transform,
+        &'static mut Inventory,
+        &'static mut Health,
+        &'static mut StatusEffects,
+    ),
+    Controlled,
+>;
+
+// Use one of the selected item, eating food or throwing weapons, and apply its effects.
+fn use_item(
+    mut commands: Commands,
+    mut player_q: UserQuery,
+    mut restore_events: EventWriter<RestoreStaminaEvent>,
+    keyboard_input: Res<Input<KeyCode>>,
+    aim_mode: Res<AimMode>,
+    item_res: Res<Items>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F) {
+        return;
+    }
+
+    let Ok((user, player, pos, mut inventory, mut health, mut status)) = player_q.get_single_mut() else {
+        return;
+    };
+
+    let Some(stack) = inventory.selected_item() else {
+        return;
+    };
+
+    // Items that don't do anything stay in hand.
+    let item = item_res.get(stack.id);
+    if item.effects.is_empty() {
+        return;
+    }
+
+    let selected = inventory.selected();
+    if inventory.take(selected, 1).is_err() {
+        return;
+    }
+
+    for effect in item.effects.iter() {
+        match *effect {
+            ItemEffect::Heal(amount) => health.heal(amount),
+            ItemEffect::RestoreStamina(amount) => restore_events.send(RestoreStaminaEvent { amount }),
+            ItemEffect::SpeedBuff { multiplier, duration, stacking } => status.apply(StatusEffect {
+                kind: StatusKind::Speed,
+                magnitude: multiplier,
+                remaining: duration,
+                stacking,
+            }),
+            ItemEffect::Explode { fuse, radius, damage, knockback } => throw_bomb(
+                &mut commands,
+                user,
+                pos.translation.truncate(),
+                throw_direction(&aim_mode, player),
+                Explosive { fuse, radius, damage, knockback },
+            ),
+        }
+    }
+}
+
+// Straight at the cursor when aiming with the mouse, otherwise the way the player faces.
+fn throw_direction(aim_mode: &AimMode, player: &Player) -> Vec2 {
+    if aim_mode.enabled && aim_mode.aim != Vec2::ZERO {
+        aim_mode.aim
+    } else {
+        player.direction.to_vec2()
+    }
+}
+
+// Selected item, drawn in the player's hand.
+#[derive(Component)]
+struct HeldItem;
+
+fn spawn_held_item(mut commands: Commands) {
+    commands.spawn((
+        SpriteBundle {
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        HeldItem,
+    ));
+}
+
+// Players holding items, with what's needed to work out where their hand is.
+type HolderQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Player,
+        &'static Transform,
+        &'static Inventory,
+        &'static SpriteAnimator,
+        Option<&'static Dead>,
+    ),
+    Without<HeldItem>,
+>;
+
+// Put selected item in player's hand for the current frame, behind them when facing away and
+// flipped when facing left, and hidden while they're dead. Runs after animation so it's never a
+// frame behind.
+fn update_held_item(
+    player_q: HolderQuery,
+    mut held_q: Query<(&mut Transform, &mut Handle<Image>, &mut Sprite, &mut Visibility), With<HeldItem>>,
+    item_res: Res<Items>,
+) {
+    let Ok((player, player_pos, inventory, animator, dead)) = player_q.get_single() else {
+        return;
+    };
+
+    let hand = animator.clip.as_ref().and_then(|clip| clip.hand(animator.frame));
+    let stack = inventory.selected_item().filter(|_| dead.is_none());
+
+    for (mut pos, mut texture, mut sprite, mut visibility) in held_q.iter_mut() {
+        let (Some(hand), Some(stack)) = (hand, stack) else {
+            if *visibility != Visibility::Hidden {
+                *visibility = Visibility::Hidden;
+            }
+            continue;
+        };
+
+        let icon = &item_res.get(stack.id).icon;
+        if *texture != *icon {
+            *texture = icon.clone();
+        }
+
+        let facing = player.direction.to_vec2();
+        let z_offset = if facing.y > 0. { -HELD_Z_OFFSET } else { HELD_Z_OFFSET };
+        let offset = hand * player_pos.scale.truncate();
+        pos.translation = player_pos.translation + offset.extend(z_offset);
+        pos.scale = Vec3::new(HELD_SCALE, HELD_SCALE, 1.);
+
+        let flip_x = facing.x < 0.;
+        if sprite.flip_x != flip_x {
+            sprite.flip_x = flip_x;
+        }
+
+        if *visibility != Visibility::Inherited {
+            *visibility = Visibility::Inherited;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::{Direction, PlayerAnimationType};
+    use crate::animator::SpriteClip;
+    use crate::bomb::Bomb;
+    use crate::inventory::{InventoryChanged, InventoryPlugin};
+    use crate::player::PLAYER_HALF_SIZE;
+    use crate::tween::TweenPlugin;
+    use bevy::render::texture::ImageTextureLoader;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    const FRAME_TIME: f32 = 0.05;
+
+    // App with a player standing at given position, and the idle item spawned.
+    fn test_app(player_pos: Vec3, inventory: Inventory) -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(AssetPlugin::default())
+            .add_asset::<Image>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+                FRAME_TIME,
+            )))
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<AimMode>()
+            .add_event::<InteractEvent>()
+            .add_event::<RestoreStaminaEvent>()
+            .insert_resource(test_items().clone())
+            .add_plugins(TweenPlugin)
+            .add_plugins(InventoryPlugin)
+            .add_plugins(ItemPlugin);
+
+        let player = app
+            .world
+            .spawn((
+                Player {
+                    animation: PlayerAnimationType::Idle(Direction::East),
+                    direction: Direction::East,
                 },
+                Collider::new(PLAYER_HALF_SIZE),
+                Transform::from_translation(player_pos),
+                inventory,
+                Health::new(100.),
+                StatusEffects::default(),
+                SpriteAnimator::default(),
+            ))
+            .id();
+
+        app.update();
+        (app, player)
+    }
+
+    fn idle_item(app: &mut App) -> Entity {
+        let ice_cream = app.world.resource::<Items>().id("ice_cream").unwrap();
+        let mut item_q = app.world.query::<(Entity, &ItemStack)>();
+        item_q
+            .iter(&app.world)
+            .find(|(_, stack)| stack.id == ice_cream)
+            .unwrap()
+            .0
+    }
+
+    fn held(app: &App, player: Entity) -> Vec<Option<(String, u32)>> {
+        let items = app.world.resource::<Items>();
+        let inventory = app.world.get::<Inventory>(player).unwrap();
+        (0..inventory.len())
+            .map(|slot| inventory.get(slot).map(|stack| (items.get(stack.id).id.clone(), stack.count)))
+            .collect()
+    }
+
+    fn ice_cream(count: u32) -> Option<(String, u32)> {
+        Some(("ice_cream".to_string(), count))
+    }
+
+    fn ice_cream_stack(count: u32) -> ItemStack {
+        test_stack("ice_cream", count)
+    }
+
+    #[test]
+    fn walking_onto_item_picks_it_up() {
+        let (mut app, player) = test_app(Vec3::new(-100., -200., 1.), Inventory::new(2));
+        let item = idle_item(&mut app);
+
+        let mut picked_up = Vec::new();
+        for _ in 0..40 {
+            app.world.get_mut::<Transform>(player).unwrap().translation.x += 5.;
+            app.update();
+
+            let events = app.world.resource::<Events<PickupEvent>>();
+            picked_up.extend(events.iter_current_update_events().map(|ev| (ev.picker, ev.stack.count)));
+        }
+
+        assert_eq!(picked_up, [(player, 1)]);
+        assert_eq!(held(&app, player), [ice_cream(1), None]);
+        assert!(app.world.get_entity(item).is_none());
+
+        let mut text_q = app.world.query::<&Text>();
+        let texts: Vec<_> = text_q.iter(&app.world).map(|text| text.sections[0].value.as_str()).collect();
+        assert_eq!(texts, ["+1 Ice Cream"]);
+
+        // Pickup effect plays, then cleans up after itself.
+        let mut effect_q = app.world.query_filtered::<(), With<PickupEffect>>();
+        let effects = effect_q.iter(&app.world).count();
+        let mut played = effects > 0;
+        for _ in 0..(PICKUP_EFFECT_TIME / FRAME_TIME) as usize + 2 {
+            app.update();
+            played |= effect_q.iter(&app.world).count() > 0;
+        }
+        assert!(played);
+        assert_eq!(effect_q.iter(&app.world).count(), 0);
+    }
+
+    #[test]
+    fn interacting_picks_up_from_a_distance() {
+        let (mut app, player) = test_app(Vec3::new(60., -200., 1.), Inventory::new(2));
+        let item = idle_item(&mut app);
+
+        app.update();
+        assert!(app.world.get_entity(item).is_some());
+
+        app.world.send_event(InteractEvent {
+            entity: item,
+            interactor: player,
+        });
+        app.update();
+
+        assert!(app.world.get_entity(item).is_none());
+        assert_eq!(held(&app, player), [ice_cream(1), None]);
+    }
+
+    #[test]
+    fn anyone_with_an_inventory_picks_up_by_interacting() {
+        let (mut app, player) = test_app(Vec3::new(-300., -200., 1.), Inventory::new(2));
+        let item = idle_item(&mut app);
+
+        // Standing right on it doesn't pick it up, only the player does that.
+        let npc = app
+            .world
+            .spawn((
+                Collider::new(PLAYER_HALF_SIZE),
+                Transform::from_xyz(100., -200., 1.),
+                Inventory::new(2),
             ))
-            .insert(ItemUI);
-        } else {
-            for item_entity in item_ui_q.iter() {
-                commands.entity(item_entity).despawn_recursive();
+            .id();
+        app.update();
+        assert!(app.world.get_entity(item).is_some());
+
+        app.world.send_event(InteractEvent {
+            entity: item,
+            interactor: npc,
+        });
+        app.update();
+
+        assert!(app.world.get_entity(item).is_none());
+        assert_eq!(held(&app, npc), [ice_cream(1), None]);
+        assert_eq!(held(&app, player), [None, None]);
+    }
+
+    #[test]
+    fn pickup_merges_into_held_stack() {
+        let mut inventory = Inventory::new(2);
+        inventory.add(&mut ice_cream_stack(2), 4).unwrap();
+        let (mut app, player) = test_app(Vec3::new(100., -200., 1.), inventory);
+
+        app.update();
+        assert_eq!(held(&app, player), [ice_cream(3), None]);
+    }
+
+    #[test]
+    fn full_inventory_leaves_rest_on_ground() {
+        let mut inventory = Inventory::new(1);
+        inventory.add(&mut ice_cream_stack(3), 4).unwrap();
+        let (mut app, player) = test_app(Vec3::new(-100., -200., 1.), inventory);
+        let item = idle_item(&mut app);
+        app.world.get_mut::<ItemStack>(item).unwrap().count = 3;
+
+        // Only one more fits, the other two stay put.
+        app.world.get_mut::<Transform>(player).unwrap().translation.x = 100.;
+        app.update();
+        assert_eq!(held(&app, player), [ice_cream(4)]);
+        assert_eq!(app.world.get::<ItemStack>(item).unwrap().count, 2);
+
+        app.update();
+        assert_eq!(app.world.get::<ItemStack>(item).unwrap().count, 2);
+        let events = app.world.resource::<Events<PickupEvent>>();
+        assert_eq!(events.iter_current_update_events().count(), 0);
+        let events = app.world.resource::<Events<InventoryChanged>>();
+        assert_eq!(events.iter_current_update_events().count(), 0);
+    }
+
+    #[test]
+    fn dropping_puts_item_back_on_ground() {
+        let (mut app, player) = test_app(Vec3::new(100., -200., 1.), Inventory::new(2));
+        app.update();
+        assert_eq!(held(&app, player), [ice_cream(1), None]);
+
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::Q);
+        app.update();
+
+        assert_eq!(held(&app, player), [None, None]);
+        let dropped = idle_item(&mut app);
+        assert_eq!(app.world.get::<ItemStack>(dropped), Some(&ice_cream_stack(1)));
+        let pos = app.world.get::<Transform>(dropped).unwrap();
+        let feet = Vec2::new(100., -200. - PLAYER_HALF_SIZE.y);
+        assert_eq!(pos.translation.truncate(), feet + Vec2::new(DROP_DISTANCE, 0.));
+
+        // Still standing on it, but it isn't picked straight back up.
+        let mut input = app.world.resource_mut::<Input<KeyCode>>();
+        input.release(KeyCode::Q);
+        input.clear();
+        for _ in 0..(DROP_IMMUNITY_TIME / FRAME_TIME) as usize - 2 {
+            app.update();
+        }
+        assert!(app.world.get_entity(dropped).is_some());
+        assert_eq!(held(&app, player), [None, None]);
+
+        for _ in 0..4 {
+            app.update();
+        }
+        assert!(app.world.get_entity(dropped).is_none());
+        assert_eq!(held(&app, player), [ice_cream(1), None]);
+    }
+
+    #[test]
+    fn dropping_takes_only_the_selected_stack() {
+        let mut inventory = Inventory::new(3);
+        inventory.add(&mut test_stack("soda", 5), 8).unwrap();
+        inventory.add(&mut test_stack("bomb", 2), 10).unwrap();
+        inventory.select(1).unwrap();
+        let (mut app, player) = test_app(Vec3::new(-300., -200., 1.), inventory);
+
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::Q);
+        app.update();
+
+        assert_eq!(held(&app, player), [Some(("soda".to_string(), 5)), None, None]);
+        let bomb = app.world.resource::<Items>().id("bomb").unwrap();
+        let mut dropped_q = app.world.query_filtered::<&ItemStack, With<PickupImmunity>>();
+        let dropped: Vec<_> = dropped_q.iter(&app.world).copied().collect();
+        assert_eq!(dropped, [ItemStack::new(bomb, 2)]);
+    }
+
+    #[test]
+    fn using_bomb_throws_it() {
+        let mut inventory = Inventory::new(2);
+        inventory.add(&mut test_stack("bomb", 2), 10).unwrap();
+        let (mut app, player) = test_app(Vec3::new(-300., -200., 1.), inventory);
+
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::F);
+        app.update();
+
+        assert_eq!(held(&app, player), [Some(("bomb".to_string(), 1)), None]);
+        let mut bomb_q = app.world.query::<&Bomb>();
+        let bomb = bomb_q.single(&app.world);
+        assert_eq!(bomb.thrower, player);
+        assert_eq!(bomb.explosive.radius, 128.);
+    }
+
+    #[test]
+    fn bombs_are_thrown_at_the_cursor_when_aiming() {
+        let player = Player {
+            animation: PlayerAnimationType::Idle(Direction::East),
+            direction: Direction::East,
+        };
+        let mut aim_mode = AimMode {
+            enabled: false,
+            aim: Vec2::new(30., 10.),
+        };
+        assert_eq!(throw_direction(&aim_mode, &player), Vec2::X);
+
+        // Not snapped to the facing direction.
+        aim_mode.enabled = true;
+        assert_eq!(throw_direction(&aim_mode, &player), Vec2::new(30., 10.));
+
+        // Cursor right on top of the player.
+        aim_mode.aim = Vec2::ZERO;
+        assert_eq!(throw_direction(&aim_mode, &player), Vec2::X);
+    }
+
+    #[test]
+    fn using_food_applies_effects_and_consumes_one() {
+        let mut inventory = Inventory::new(2);
+        inventory.add(&mut test_stack("soda", 2), 8).unwrap();
+        inventory.add(&mut ice_cream_stack(1), 4).unwrap();
+        let (mut app, player) = test_app(Vec3::new(-300., -200., 1.), inventory);
+        app.world.get_mut::<Health>(player).unwrap().current = 50.;
+
+        let use_selected = |app: &mut App| {
+            let mut input = app.world.resource_mut::<Input<KeyCode>>();
+            input.release(KeyCode::F);
+            input.clear();
+            input.press(KeyCode::F);
+            app.update();
+        };
+
+        // Soda restores stamina and speeds player up for a while.
+        use_selected(&mut app);
+        let events = app.world.resource::<Events<RestoreStaminaEvent>>();
+        let restored: Vec<f32> = events.iter_current_update_events().map(|ev| ev.amount).collect();
+        assert_eq!(restored, [50.]);
+        let status = app.world.get::<StatusEffects>(player).unwrap();
+        assert_eq!(status.multiplier(StatusKind::Speed), 1.5);
+        assert_eq!(held(&app, player)[0], Some(("soda".to_string(), 1)));
+
+        // Last one empties the slot.
+        use_selected(&mut app);
+        assert_eq!(held(&app, player), [None, ice_cream(1)]);
+        use_selected(&mut app);
+        assert_eq!(held(&app, player), [None, ice_cream(1)]);
+
+        // Ice cream heals.
+        app.world.get_mut::<Inventory>(player).unwrap().select(1).unwrap();
+        use_selected(&mut app);
+        assert_eq!(app.world.get::<Health>(player).unwrap().current, 70.);
+        assert_eq!(held(&app, player), [None, None]);
+    }
+
+    #[test]
+    fn held_item_follows_hand_and_facing() {
+        let mut inventory = Inventory::new(2);
+        inventory.add(&mut test_stack("soda", 1), 8).unwrap();
+        let (mut app, player) = test_app(Vec3::new(-300., -200., 1.), inventory);
+        app.world.get_mut::<Transform>(player).unwrap().scale = Vec3::new(0.5, 0.5, 0.);
+
+        let mut clip = SpriteClip {
+            atlas: Handle::default(),
+            len: 2,
+            frames: Vec::new(),
+            frame_time: 10.,
+            loop_mode: LoopMode::Loop,
+            events: Vec::new(),
+            flip_x: false,
+            speed_scale: None,
+            hands: vec![Vec2::new(20., -10.), Vec2::new(20., -6.)],
+        };
+
+        let held = |app: &mut App| {
+            let mut held_q = app.world.query_filtered::<(&Transform, &Sprite, &Visibility), With<HeldItem>>();
+            let (pos, sprite, visibility) = held_q.single(&app.world);
+            (pos.translation, sprite.flip_x, *visibility)
+        };
+        let set = |app: &mut App, clip: &SpriteClip, frame: usize, direction: Direction| {
+            let mut animator = app.world.get_mut::<SpriteAnimator>(player).unwrap();
+            animator.play_from(clip, frame);
+            app.world.get_mut::<Player>(player).unwrap().direction = direction;
+            app.update();
+        };
+
+        // Facing the camera, held in front.
+        set(&mut app, &clip, 1, Direction::SouthEast);
+        assert_eq!(held(&mut app), (Vec3::new(-290., -203., 1.01), false, Visibility::Inherited));
+
+        // Facing away, held behind.
+        set(&mut app, &clip, 0, Direction::NorthEast);
+        assert_eq!(held(&mut app).0, Vec3::new(-290., -205., 0.99));
+
+        // Mirrored clip for facing left flips item into the other hand.
+        clip.flip_x = true;
+        set(&mut app, &clip, 0, Direction::West);
+        assert_eq!(held(&mut app), (Vec3::new(-310., -205., 1.01), true, Visibility::Inherited));
+
+        // Put away while dead.
+        app.world.entity_mut(player).insert(Dead {
+            timer: Timer::from_seconds(1., TimerMode::Once),
+        });
+        app.update();
+        assert_eq!(held(&mut app).2, Visibility::Hidden);
+
+        app.world.entity_mut(player).remove::<Dead>();
+        app.update();
+        assert_eq!(held(&mut app).2, Visibility::Inherited);
+
+        // Nothing selected, nothing held.
+        app.world.get_mut::<Inventory>(player).unwrap().select(1).unwrap();
+        app.update();
+        assert_eq!(held(&mut app).2, Visibility::Hidden);
+    }
+
+    #[test]
+    fn item_database_resolves_ids() {
+        let items = test_items();
+
+        let soda = items.id("soda").unwrap();
+        assert_ne!(items.id("ice_cream"), Some(soda));
+        assert_eq!(items.get(soda).name, "Soda");
+        assert_eq!(items.get(soda).category, ItemCategory::Food);
+        assert!(items.id("pickle").is_none());
+    }
+
+    fn asset_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(AssetPlugin::default())
+            .add_asset::<Image>()
+            .init_asset_loader::<ImageTextureLoader>()
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<AimMode>()
+            .add_event::<InteractEvent>()
+            .add_event::<RestoreStaminaEvent>()
+            .add_plugins(TweenPlugin)
+            .add_plugins(ItemPlugin);
+        app
+    }
+
+    // Update until the database and its icons have loaded from disk.
+    fn wait_for_items(app: &mut App) {
+        for _ in 0..1000 {
+            app.update();
+            if !app.world.resource::<Items>().is_empty() {
+                break;
             }
+            std::thread::sleep(std::time::Duration::from_millis(10));
         }
     }
+
+    #[test]
+    fn database_loads_through_asset_server() {
+        let mut app = asset_app();
+        wait_for_items(&mut app);
+
+        let items = app.world.resource::<Items>();
+        let bomb = items.get(items.id("bomb").unwrap());
+
+        // Cut out of its sprite sheet, so smaller than the whole sheet.
+        let images = app.world.resource::<Assets<Image>>();
+        let icon = images.get(&bomb.icon).unwrap();
+        assert!(icon.size().x <= 48. && icon.size().y <= 48.);
+
+        app.update();
+        assert_eq!(app.world.query::<&ItemStack>().iter(&app.world).count(), 2);
+    }
+
+    #[test]
+    fn missing_icons_reject_the_database() {
+        let mut app = asset_app();
+        wait_for_items(&mut app);
+        let soda = app.world.resource::<Items>().id("soda").unwrap();
+
+        let ItemDatabase(definitions) = ron::from_str(
+            r#"[
+                (id: "soda", name: "Soda", icon_path: "item/food/soda.png", category: Food, max_stack: 2),
+                (id: "pickle", name: "Pickle", icon_path: "item/food/pickle.png", category: Food, max_stack: 8),
+            ]"#,
+        )
+        .unwrap();
+        let asset_server = app.world.resource::<AssetServer>().clone();
+        app.world.resource_mut::<Items>().load_icons(&definitions, &asset_server);
+
+        for _ in 0..1000 {
+            app.update();
+            if app.world.resource::<Items>().pending.is_none() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        // Kept the items from before.
+        let items = app.world.resource::<Items>();
+        assert!(items.pending.is_none());
+        assert!(items.id("pickle").is_none());
+        assert_eq!(items.max_stack(soda), 8);
+    }
+
+    #[test]
+    fn reloading_keeps_ids_and_ignores_bad_databases() {
+        let mut items = Items::parse(
+            r#"[(id: "soda", name: "Soda", icon_path: "item/food/soda.png", category: Food, max_stack: 8)]"#,
+        )
+        .unwrap();
+        let soda = items.id("soda").unwrap();
+
+        // New item added in front, and soda changed.
+        let ItemDatabase(definitions) = ron::from_str(
+            r#"[
+                (id: "ice_cream", name: "Ice Cream", icon_path: "item/food/ice_cream.png", category: Food, max_stack: 4),
+                (id: "soda", name: "Soda", icon_path: "item/food/soda.png", category: Food, max_stack: 6),
+            ]"#,
+        )
+        .unwrap();
+        items.update(&definitions).unwrap();
+        assert_eq!(items.id("soda"), Some(soda));
+        assert_eq!(items.max_stack(soda), 6);
+        assert!(items.id("ice_cream").is_some());
+
+        let ItemDatabase(definitions) = ron::from_str(
+            r#"[(id: "soda", name: "Soda", icon_path: "item/food/soda.png", category: Food, max_stack: 0)]"#,
+        )
+        .unwrap();
+        assert!(items.update(&definitions).is_err());
+        assert_eq!(items.max_stack(soda), 6);
+    }
+
+    #[test]
+    fn item_database_rejects_bad_definitions() {
+        let err = Items::parse(
+            r#"[
+                (id: "soda", name: "Soda", icon_path: "item/food/soda.png", category: Food, max_stack: 8),
+                (id: "soda", name: "Soda", icon_path: "item/food/soda.png", category: Food, max_stack: 8),
+                (id: "pickle", name: "Pickle", icon_path: "item/food/pickle.png", category: Food, max_stack: 0),
+            ]"#,
+        )
+        .err()
+        .unwrap();
+
+        assert_eq!(
+            err.lines().collect::<Vec<_>>(),
+            [
+                "soda: defined more than once",
+                "pickle: max_stack has to be at least 1",
+            ]
+        );
+    }
 }
@@ -1,13 +1,29 @@
+use bevy::asset::ChangeWatcher;
 use bevy::prelude::*;
 use bevy::window::*;
+use std::time::Duration;
 
 mod animation;
+mod animation_state;
+mod animator;
+mod atlas;
+mod bomb;
 mod camera;
+mod collision;
+mod dash;
 mod debug;
+mod health;
+mod hotbar;
+mod interact;
+mod inventory;
 mod item;
 mod map;
 mod mouse;
+mod platformer;
 mod player;
+mod stamina;
+mod status;
+mod tween;
 
 fn main() {
     let mut app = App::new();
@@ -15,6 +31,11 @@ fn main() {
     app.add_plugins(
             DefaultPlugins
                 .set(ImagePlugin::default_nearest()) // Necessary to not spawn blurry sprites.
+                .set(AssetPlugin {
+                    // Hot reload assets, like animation manifests, when they change on disk.
+                    watch_for_changes: ChangeWatcher::with_delay(Duration::from_millis(200)),
+                    ..default()
+                })
                 .set(WindowPlugin {
                     primary_window: Some(Window {
                         title: "Untitled Game".into(),
@@ -34,12 +55,25 @@ fn main() {
                 }),
     );
     app.insert_resource(ClearColor(Color::rgb(0., 0., 0.))); // Set background color to black.
+    app.insert_resource(map::Level::from_args(std::env::args())); // Pick a level with --level <name>.
     app.add_plugins(camera::CameraPlugin);
     app.add_plugins(debug::DebugPlugin);
     app.add_plugins(map::MapPlugin);
     app.add_plugins(mouse::MousePlugin);
     app.add_plugins(player::PlayerPlugin);
+    app.add_plugins(platformer::PlatformerPlugin);
+    app.add_plugins(stamina::StaminaPlugin);
+    app.add_plugins(status::StatusPlugin);
+    app.add_plugins(health::HealthPlugin);
+    app.add_plugins(dash::DashPlugin);
+    app.add_plugins(interact::InteractPlugin);
+    app.add_plugins(inventory::InventoryPlugin);
+    app.add_plugins(item::ItemPlugin);
+    app.add_plugins(hotbar::HotbarPlugin);
+    app.add_plugins(bomb::BombPlugin);
     app.add_plugins(animation::AnimationPlugin);
+    app.add_plugins(animator::AnimatorPlugin);
+    app.add_plugins(tween::TweenPlugin);
 
     app.run();
 }
@@ -1,3 +1,6 @@
+use crate::collision::{Collider, OneWayPlatform, Solid};
+use crate::health::{Checkpoint, Hazard};
+use crate::player::ControlMode;
 use bevy::prelude::*;
 use std::fs::File;
 use std::io::prelude::*;
@@ -8,27 +11,65 @@ pub struct MapPlugin;
 
 impl Plugin for MapPlugin {
     fn build(&self, app: &mut App) {
-            app.add_systems(PreStartup, spawn_map);
+            app.init_resource::<Level>()
+                .init_resource::<PlayerSpawn>()
+                .add_systems(PreStartup, spawn_map);
     }
 }
 
-#[derive(Component)]
-pub enum GroundTile {
-    Grass,
-    Dirt,
-    Rock,
+// Level file to build the map from, relative to the assets folder.
+#[derive(Resource)]
+pub struct Level {
+    pub path: String,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Level {
+            path: "map/level.txt".to_string(),
+        }
+    }
 }
 
+impl Level {
+    // Level picked on the command line, like `--level platformer` for map/platformer.txt, or the
+    // default level if there isn't one.
+    pub fn from_args(args: impl Iterator<Item = String>) -> Self {
+        let mut args = args.skip_while(|arg| arg != "--level").skip(1);
+
+        match args.next() {
+            Some(name) => Level {
+                path: format!("map/{}.txt", name),
+            },
+            None => Level::default(),
+        }
+    }
+}
+
+// Where the player starts, set by a 'P' in the level file.
+#[derive(Resource, Default)]
+pub struct PlayerSpawn(pub Vec3);
+
+// Tile that explosions can blow away.
+#[derive(Component)]
+pub struct Destructible;
+
 // Spawn tile blocks.
+//
+// Level files are read bottom row first. An optional first line of "mode: platformer" or
+// "mode: topdown" picks the player's controller for that level.
+// 0 = ground, 1 = rock (solid), 2 = one-way platform, 3 = breakable rock (solid, destroyed by
+// explosions), S = spikes, P = player spawn, C = checkpoint.
 fn spawn_map(
     mut commands: Commands,
+    level: Res<Level>,
     asset_server: Res<AssetServer>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
 ) {
     const TILE_SIZE: f32 = 16.; // Size of single tile in tileset.
     const SCALE: f32 = 4.;
     const SCALED_TILE_SIZE: f32 = SCALE * TILE_SIZE;
-    const MAP_SIZE: i32 = 20;
+    const SPIKE_DAMAGE: f32 = 20.;
 
     // Cut out rock sprite.
     let texture_handle = asset_server.load("map/rock_tiles.png");
@@ -54,17 +95,29 @@ fn spawn_map(
     );
     let tile_texture = texture_atlases.add(texture_atlas);
 
+    let tile_collider = Collider::new(Vec2::splat(SCALED_TILE_SIZE / 2.));
+
     // Open level file.
-    let mut file = File::open("assets/map/level.txt").unwrap();
+    let file = File::open(format!("assets/{}", level.path)).unwrap();
     let reader = BufReader::new(file);
 
-    let mut x: f32 = 0.;
     let mut y: f32 = 0.;
 
     for line in reader.lines() {
+        let line = line.expect("Failed to get line for map.");
+
+        if let Some(mode) = line.strip_prefix("mode:") {
+            match mode.trim() {
+                "platformer" => commands.insert_resource(ControlMode::Platformer),
+                "topdown" => commands.insert_resource(ControlMode::TopDown),
+                other => warn!("Unknown level mode \"{}\" in {}.", other, level.path),
+            }
+            continue;
+        }
+
         y += 1.;
-        x = 0.;
-        for char in line.expect("Failed to get line for map.").chars() {
+        let mut x: f32 = 0.;
+        for char in line.chars() {
             x += 1.;
             commands.spawn(SpriteSheetBundle {
                 texture_atlas: tile_texture.clone(),
@@ -80,21 +133,138 @@ fn spawn_map(
                 ..default()
             });
 
-            if char == '1' {
-                commands.spawn(SpriteSheetBundle {
-                    texture_atlas: rock_texture.clone(),
-                    sprite: TextureAtlasSprite {
-                        index: 0,
-                        ..default()
-                    },
-                    transform: Transform {
-                        translation: Vec3::new(SCALED_TILE_SIZE * x, SCALED_TILE_SIZE * y, 0.9),
-                        scale: Vec3::new(SCALE, SCALE, 0.),
-                        ..default()
-                    },
-                    ..default()
-                });
+            let translation = Vec3::new(SCALED_TILE_SIZE * x, SCALED_TILE_SIZE * y, 0.9);
+
+            match char {
+                '1' => {
+                    commands.spawn((
+                        SpriteSheetBundle {
+                            texture_atlas: rock_texture.clone(),
+                            sprite: TextureAtlasSprite {
+                                index: 0,
+                                ..default()
+                            },
+                            transform: Transform {
+                                translation,
+                                scale: Vec3::new(SCALE, SCALE, 0.),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        Solid,
+                        tile_collider,
+                    ));
+                }
+                '3' => {
+                    commands.spawn((
+                        SpriteSheetBundle {
+                            texture_atlas: rock_texture.clone(),
+                            sprite: TextureAtlasSprite {
+                                index: 2,
+                                ..default()
+                            },
+                            transform: Transform {
+                                translation,
+                                scale: Vec3::new(SCALE, SCALE, 0.),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        Solid,
+                        Destructible,
+                        tile_collider,
+                    ));
+                }
+                '2' => {
+                    // Thin ledge along the top edge of the tile.
+                    let ledge_height = SCALED_TILE_SIZE / 4.;
+
+                    commands.spawn((
+                        SpriteSheetBundle {
+                            texture_atlas: rock_texture.clone(),
+                            sprite: TextureAtlasSprite {
+                                index: 1,
+                                ..default()
+                            },
+                            transform: Transform {
+                                translation: translation
+                                    + Vec3::new(0., (SCALED_TILE_SIZE - ledge_height) / 2., 0.),
+                                scale: Vec3::new(SCALE, SCALE / 4., 0.),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        OneWayPlatform,
+                        Collider::new(Vec2::new(SCALED_TILE_SIZE / 2., ledge_height / 2.)),
+                    ));
+                }
+                'S' => {
+                    commands.spawn((
+                        SpriteSheetBundle {
+                            texture_atlas: rock_texture.clone(),
+                            sprite: TextureAtlasSprite {
+                                index: 3,
+                                ..default()
+                            },
+                            transform: Transform {
+                                translation,
+                                scale: Vec3::new(SCALE, SCALE, 0.),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        Hazard {
+                            damage: SPIKE_DAMAGE,
+                        },
+                        tile_collider,
+                    ));
+                }
+                'C' => {
+                    commands.spawn((
+                        SpatialBundle::from_transform(Transform::from_translation(translation)),
+                        Checkpoint,
+                        tile_collider,
+                    ));
+                }
+                'P' => {
+                    commands.insert_resource(PlayerSpawn(Vec3::new(translation.x, translation.y, 1.)));
+                }
+                _ => {}
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().into_iter()
+    }
+
+    #[test]
+    fn level_comes_from_args() {
+        assert_eq!(Level::from_args(args(&["game"])).path, "map/level.txt");
+        assert_eq!(
+            Level::from_args(args(&["game", "--level", "platformer"])).path,
+            "map/platformer.txt"
+        );
+    }
+
+    #[test]
+    fn platformer_level_switches_control_mode() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(AssetPlugin::default())
+            .add_asset::<Image>()
+            .add_asset::<TextureAtlas>()
+            .init_resource::<ControlMode>()
+            .insert_resource(Level::from_args(args(&["game", "--level", "platformer"])))
+            .add_plugins(MapPlugin);
+        app.update();
+
+        assert_eq!(*app.world.resource::<ControlMode>(), ControlMode::Platformer);
+        assert_ne!(app.world.resource::<PlayerSpawn>().0, Vec3::ZERO);
+    }
+}
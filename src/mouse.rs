@@ -1,22 +1,29 @@
+use crate::animation::Direction;
+use crate::player::{ControlMode, Player};
+use bevy::input::mouse::MouseMotion;
+use bevy::prelude::*;
 use bevy::window::*;
-use bevy::{
-    input::{
-        mouse::{MouseButtonInput, MouseMotion, MouseWheel},
-        touchpad::{TouchpadMagnify, TouchpadRotate},
-    },
-    prelude::*,
-};
 
 #[derive(Component)]
 pub struct MousePlugin;
 
 impl Plugin for MousePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_cursor)
-            .add_systems(Update, update_cursor);
+        app.init_resource::<AimMode>()
+            .add_systems(Startup, spawn_cursor)
+            .add_systems(Update, update_cursor)
+            .add_systems(Update, toggle_aim_mode)
+            .add_systems(Update, aim_player_direction.after(update_cursor));
     }
 }
 
+// When enabled, player faces the cursor and movement keys only move the player.
+#[derive(Resource, Default)]
+pub struct AimMode {
+    pub enabled: bool,
+    pub aim: Vec2, // From the player to the cursor, for throwing towards it.
+}
+
 #[derive(Component)]
 struct Mouse;
 
@@ -40,13 +47,10 @@ fn spawn_cursor(
 }
 
 fn update_cursor(
-    mut commands: Commands,
     mut window_q: Query<&mut Window, With<PrimaryWindow>>,
-    mouse_entity_q: Query<Entity, With<Mouse>>,
     mut mouse_pos_q: Query<&mut Transform, With<Mouse>>,
     mut mouse_events: EventReader<MouseMotion>,
 ) {
-    let mouse_e = mouse_entity_q.single();
     let mut mouse_pos = mouse_pos_q.single_mut();
     let mut window = window_q.single_mut();
 
@@ -59,3 +63,122 @@ fn update_cursor(
         mouse_pos.translation.y += -ev.delta.y;
     }
 }
+
+fn toggle_aim_mode(mut aim_mode: ResMut<AimMode>, keyboard_input: Res<Input<KeyCode>>) {
+    if keyboard_input.just_pressed(KeyCode::Tab) {
+        aim_mode.enabled = !aim_mode.enabled;
+    }
+}
+
+// Face player towards cursor, snapped to the eight animation directions.
+fn aim_player_direction(
+    mut aim_mode: ResMut<AimMode>,
+    mode: Res<ControlMode>,
+    mut player_q: Query<(&mut Player, &Transform)>,
+    mouse_q: Query<&Transform, (With<Mouse>, Without<Player>)>,
+) {
+    if !aim_mode.enabled {
+        return;
+    }
+
+    let (Ok((mut player, player_pos)), Ok(mouse_pos)) =
+        (player_q.get_single_mut(), mouse_q.get_single())
+    else {
+        return;
+    };
+
+    let mut aim = (mouse_pos.translation - player_pos.translation).truncate();
+
+    // Side view only ever faces left or right.
+    if *mode == ControlMode::Platformer {
+        aim.y = 0.;
+    }
+
+    aim_mode.aim = aim;
+    if let Some(direction) = Direction::from_vec2(aim) {
+        player.direction = direction;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::PlayerAnimationType;
+    use crate::player::update_player_direction;
+
+    fn test_app(mode: ControlMode) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<AimMode>()
+            .insert_resource(mode)
+            .add_systems(
+                Update,
+                (toggle_aim_mode, update_player_direction, aim_player_direction).chain(),
+            );
+
+        app.world.spawn((
+            Player {
+                animation: PlayerAnimationType::Idle(Direction::South),
+                direction: Direction::South,
+            },
+            Transform::from_xyz(100., 100., 1.),
+        ));
+        app.world.spawn((Mouse, Transform::from_xyz(150., 150., 0.)));
+        app
+    }
+
+    fn press(app: &mut App, keys: &[KeyCode]) {
+        let mut input = app.world.resource_mut::<Input<KeyCode>>();
+        for key in keys {
+            input.press(*key);
+        }
+        app.update();
+        app.world.resource_mut::<Input<KeyCode>>().reset_all();
+    }
+
+    fn move_cursor(app: &mut App, x: f32, y: f32) {
+        let mut cursor_q = app.world.query_filtered::<&mut Transform, With<Mouse>>();
+        cursor_q.single_mut(&mut app.world).translation = Vec3::new(x, y, 0.);
+        app.update();
+    }
+
+    fn facing(app: &mut App) -> Direction {
+        app.world.query::<&Player>().single(&app.world).direction
+    }
+
+    #[test]
+    fn tab_toggles_aiming_at_the_cursor() {
+        let mut app = test_app(ControlMode::TopDown);
+
+        press(&mut app, &[KeyCode::D]);
+        assert!(!app.world.resource::<AimMode>().enabled);
+        assert_eq!(facing(&mut app), Direction::East);
+
+        // Cursor wins over movement keys while aiming.
+        press(&mut app, &[KeyCode::Tab, KeyCode::D]);
+        assert!(app.world.resource::<AimMode>().enabled);
+        assert_eq!(facing(&mut app), Direction::NorthEast);
+        assert_eq!(app.world.resource::<AimMode>().aim, Vec2::new(50., 50.));
+
+        move_cursor(&mut app, 40., 90.);
+        assert_eq!(facing(&mut app), Direction::West);
+
+        press(&mut app, &[KeyCode::Tab, KeyCode::S]);
+        assert!(!app.world.resource::<AimMode>().enabled);
+        assert_eq!(facing(&mut app), Direction::South);
+    }
+
+    #[test]
+    fn platformer_aim_only_faces_left_or_right() {
+        let mut app = test_app(ControlMode::Platformer);
+        press(&mut app, &[KeyCode::Tab]);
+
+        move_cursor(&mut app, 90., 300.);
+        assert_eq!(facing(&mut app), Direction::West);
+        assert_eq!(app.world.resource::<AimMode>().aim, Vec2::new(-10., 0.));
+
+        move_cursor(&mut app, 110., -300.);
+        assert_eq!(facing(&mut app), Direction::East);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::collision::{collect_obstacles, move_and_collide, Collider, ObstacleQuery};
+use crate::dash::Dash;
+use crate::player::{ControlMode, Controlled, Player, RUN_MULTIPLIER, WALK_SPEED};
+use crate::stamina::Stamina;
+use crate::status::{StatusEffects, StatusKind};
+use bevy::prelude::*;
+
+const GRAVITY: f32 = 1800.;
+const MAX_FALL_SPEED: f32 = 900.;
+const JUMP_SPEED: f32 = 650.;
+const JUMP_CUT: f32 = 0.4; // Upward velocity kept when jump is released early, for variable height jumps.
+const COYOTE_TIME: f32 = 0.1; // Seconds after walking off a ledge that a jump is still allowed.
+const JUMP_BUFFER_TIME: f32 = 0.12; // Seconds a jump press is remembered before landing.
+
+const JUMP_KEYS: [KeyCode; 3] = [KeyCode::Space, KeyCode::W, KeyCode::Up];
+
+pub struct PlatformerPlugin;
+
+impl Plugin for PlatformerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (platformer_input, platformer_physics)
+                .chain()
+                .run_if(resource_equals(ControlMode::Platformer)),
+        );
+    }
+}
+
+#[derive(Component, Default)]
+pub struct PlatformerBody {
+    pub velocity: Vec2,
+    pub grounded: bool,
+    coyote_timer: f32,      // Time left to jump after leaving the ground.
+    jump_buffer_timer: f32, // Time left for a buffered jump press.
+}
+
+// Horizontal movement, jumping, coyote time and jump buffering.
+fn platformer_input(
+    mut body_q: Query<(&mut PlatformerBody, &mut Stamina, &Dash, &StatusEffects), Controlled>,
+    keyboard_input: Res<Input<KeyCode>>,
+    time: Res<Time>,
+) {
+    let Ok((mut body, mut stamina, dash, status)) = body_q.get_single_mut() else {
+        return;
+    };
+
+    if dash.is_dashing() {
+        stamina.try_sprint(false);
+        return;
+    }
+
+    let mut direction = 0.;
+    if keyboard_input.any_pressed([KeyCode::A, KeyCode::Left]) {
+        direction -= 1.;
+    }
+    if keyboard_input.any_pressed([KeyCode::D, KeyCode::Right]) {
+        direction += 1.;
+    }
+
+    let mut speed = WALK_SPEED * status.multiplier(StatusKind::Speed);
+    let wants_to_sprint = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight])
+        && direction != 0.;
+    if stamina.try_sprint(wants_to_sprint) {
+        speed *= RUN_MULTIPLIER;
+    }
+    body.velocity.x = direction * speed;
+
+    let dt = time.delta_seconds();
+
+    if body.grounded {
+        body.coyote_timer = COYOTE_TIME;
+    } else {
+        body.coyote_timer -= dt;
+    }
+
+    if keyboard_input.any_just_pressed(JUMP_KEYS) {
+        body.jump_buffer_timer = JUMP_BUFFER_TIME;
+    } else {
+        body.jump_buffer_timer -= dt;
+    }
+
+    if body.jump_buffer_timer > 0. && body.coyote_timer > 0. {
+        body.velocity.y = JUMP_SPEED;
+        body.grounded = false;
+        body.jump_buffer_timer = 0.;
+        body.coyote_timer = 0.;
+    }
+
+    // Releasing jump early cuts the jump short.
+    if keyboard_input.any_just_released(JUMP_KEYS) && body.velocity.y > 0. {
+        body.velocity.y *= JUMP_CUT;
+    }
+}
+
+// Apply gravity and move the player against the level's solid tiles.
+fn platformer_physics(
+    mut player_q: Query<(&mut PlatformerBody, &mut Transform, &Collider, &Dash), With<Player>>,
+    obstacle_q: ObstacleQuery,
+    time: Res<Time>,
+) {
+    let Ok((mut body, mut pos, collider, dash)) = player_q.get_single_mut() else {
+        return;
+    };
+
+    // Dashes ignore gravity, and leave the player falling from rest.
+    if dash.is_dashing() {
+        body.velocity = Vec2::ZERO;
+        return;
+    }
+
+    let dt = time.delta_seconds();
+
+    body.velocity.y = (body.velocity.y - GRAVITY * dt).max(-MAX_FALL_SPEED);
+
+    let result = move_and_collide(
+        pos.translation.truncate(),
+        collider.half_size,
+        body.velocity * dt,
+        &collect_obstacles(&obstacle_q),
+    );
+
+    pos.translation.x = result.position.x;
+    pos.translation.y = result.position.y;
+
+    if result.hit_floor || result.hit_ceiling {
+        body.velocity.y = 0.;
+    }
+    body.grounded = result.hit_floor;
+}
@@ -1,35 +1,69 @@
 use crate::animation::{Direction, PlayerAnimationType};
+use crate::animation_state::AnimationParams;
+use crate::animator::{AnimationEvent, SpriteAnimator};
+use crate::collision::Collider;
+use crate::dash::Dash;
+use crate::health::{Dead, Health};
+use crate::inventory::Inventory;
+use crate::map::PlayerSpawn;
+use crate::mouse::AimMode;
+use crate::platformer::PlatformerBody;
+use crate::stamina::Stamina;
+use crate::status::{StatusEffects, StatusKind};
+use crate::tween::{Ease, Lens, Tween, TweenCompleted};
 use bevy::prelude::*;
 
+// Half size of the player's hitbox, much smaller than the sprite frame itself.
+pub const PLAYER_HALF_SIZE: Vec2 = Vec2::new(16., 24.);
+pub const WALK_SPEED: f32 = 250.;
+pub const RUN_MULTIPLIER: f32 = 2.;
+const MAX_STAMINA: f32 = 100.;
+const MAX_HEALTH: f32 = 100.;
+const INVENTORY_SIZE: usize = 8;
+const DUST_TIME: f32 = 0.3; // Seconds a footstep's dust puff takes to fade.
+const DUST_SIZE: f32 = 6.;
+
+// Filter for the player while alive, for systems driven by the player's input.
+pub type Controlled = (With<Player>, Without<Dead>);
+
 #[derive(Component)]
 pub struct Player {
     pub animation: PlayerAnimationType,
     pub direction: Direction,
-    pub frame_time: f32, // To compare player's frame_time to animation's frame_time.
 }
 
 impl Player {
 }
 
+// How the player is controlled, chosen by the level being played.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ControlMode {
+    #[default]
+    TopDown, // Free 8-directional movement.
+    Platformer, // Side view with gravity and jumping.
+}
+
 #[derive(Component)]
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_player)
-            .add_systems(Update, player_movement)
-            .add_systems(Update, update_player_direction);
+        app.init_resource::<ControlMode>()
+            .add_systems(Startup, spawn_player)
+            .add_systems(Update, player_movement.run_if(resource_equals(ControlMode::TopDown)))
+            .add_systems(Update, update_player_direction)
+            .add_systems(Update, (spawn_footstep_dust, despawn_footstep_dust));
     }
 }
 
-fn spawn_player(mut commands: Commands) {
+fn spawn_player(mut commands: Commands, spawn: Res<PlayerSpawn>) {
     const SCALE: f32 = 0.75;
 
     commands.spawn((
         SpriteSheetBundle {
             transform: Transform {
                 scale: Vec3::new(SCALE, SCALE, 0.),
-                translation: Vec3::new(0., 0., 1.), // Setting z-index to 1 will make sure player is drawn over everything else.
+                translation: spawn.0.truncate().extend(1.), // Setting z-index to 1 will make sure player is drawn over everything else.
                 ..default()
             },
             ..default()
@@ -37,23 +71,33 @@ fn spawn_player(mut commands: Commands) {
         Player {
             animation: PlayerAnimationType::Idle(Direction::South),
             direction: Direction::South,
-            frame_time: 0.6,
         },
+        SpriteAnimator::default(),
+        AnimationParams::default(),
+        Collider::new(PLAYER_HALF_SIZE),
+        PlatformerBody::default(),
+        Stamina::new(MAX_STAMINA),
+        Health::new(MAX_HEALTH),
+        Dash::default(),
+        Inventory::new(INVENTORY_SIZE),
+        StatusEffects::default(),
     ));
 }
 
 fn player_movement(
-    mut player_q: Query<(&mut Player, &mut Transform)>,
+    mut player_q: Query<(&mut Transform, &mut Stamina, &Dash, &StatusEffects), Controlled>,
     keyboard_input: Res<Input<KeyCode>>,
     time: Res<Time>,
 ) {
-    let mut SPEED: f32 = 250.;
-
-    if keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
-        SPEED *= 2.;
+    let Ok((mut pos, mut stamina, dash, status)) = player_q.get_single_mut() else {
+        return;
+    };
+
+    // Dash takes over movement until it's done.
+    if dash.is_dashing() {
+        stamina.try_sprint(false);
+        return;
     }
-
-    let (mut player, mut pos) = player_q.single_mut();
     let mut direction = Vec3::ZERO;
 
     if keyboard_input.any_pressed([KeyCode::A, KeyCode::Left]) {
@@ -76,14 +120,42 @@ fn player_movement(
         direction = direction.normalize(); // Normalizing direction for diagonal movement.
     }
 
+    // Sprinting costs stamina, and only counts while actually moving.
+    let mut speed = WALK_SPEED * status.multiplier(StatusKind::Speed);
+    let wants_to_sprint = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight])
+        && direction != Vec3::ZERO;
+    if stamina.try_sprint(wants_to_sprint) {
+        speed *= RUN_MULTIPLIER;
+    }
+
     // Setting translation vector to product of updated direction vector
     // delta_seconds returns time elapsed since last frame, used to make movement frame-rate independent
-    pos.translation += direction * SPEED * time.delta_seconds();
+    pos.translation += direction * speed * time.delta_seconds();
 }
 
-fn update_player_direction(mut player_q: Query<&mut Player>, keyboard_input: Res<Input<KeyCode>>) {
+pub fn update_player_direction(
+    mut player_q: Query<&mut Player>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mode: Res<ControlMode>,
+    aim_mode: Res<AimMode>,
+) {
+    // Facing follows the cursor instead while aiming.
+    if aim_mode.enabled {
+        return;
+    }
+
     let mut player = player_q.single_mut();
 
+    // Side view only ever faces left or right.
+    if *mode == ControlMode::Platformer {
+        if keyboard_input.any_pressed([KeyCode::A, KeyCode::Left]) {
+            player.direction = Direction::West;
+        } else if keyboard_input.any_pressed([KeyCode::D, KeyCode::Right]) {
+            player.direction = Direction::East;
+        }
+        return;
+    }
+
     if keyboard_input.any_pressed([KeyCode::A, KeyCode::Left]) && keyboard_input.any_pressed([KeyCode::W, KeyCode::Up])
     {
         player.direction = Direction::NorthWest;
@@ -106,3 +178,60 @@ fn update_player_direction(mut player_q: Query<&mut Player>, keyboard_input: Res
         player.direction = Direction::East;
     }
 }
+
+#[derive(Component)]
+struct FootstepDust;
+
+// Kick up a puff of dust at the player's feet on the walk and run clips' footstep frames.
+fn spawn_footstep_dust(
+    mut commands: Commands,
+    player_q: Query<(&Transform, &Collider), With<Player>>,
+    mut animation_events: EventReader<AnimationEvent>,
+) {
+    for ev in animation_events.iter() {
+        if ev.name != "footstep" {
+            continue;
+        }
+
+        let Ok((pos, collider)) = player_q.get(ev.entity) else {
+            continue;
+        };
+
+        // Just under the player, over the ground tiles.
+        let feet = pos.translation.truncate() - Vec2::new(0., collider.half_size.y);
+
+        commands
+            .spawn(SpatialBundle::from_transform(Transform::from_translation(feet.extend(0.95))))
+            .insert(FootstepDust)
+            .insert(
+                Tween::new(Lens::Scale(Vec3::ONE, Vec3::splat(2.)), DUST_TIME)
+                    .with_ease(Ease::QuadOut),
+            )
+            .with_children(|parent| {
+                parent
+                    .spawn(SpriteBundle {
+                        sprite: Sprite {
+                            custom_size: Some(Vec2::splat(DUST_SIZE)),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .insert(Tween::new(
+                        Lens::Color(Color::rgba(0.8, 0.75, 0.65, 0.8), Color::rgba(0.8, 0.75, 0.65, 0.)),
+                        DUST_TIME,
+                    ));
+            });
+    }
+}
+
+fn despawn_footstep_dust(
+    mut commands: Commands,
+    mut tween_events: EventReader<TweenCompleted>,
+    dust_q: Query<(), With<FootstepDust>>,
+) {
+    for ev in tween_events.iter() {
+        if dust_q.contains(ev.entity) {
+            commands.entity(ev.entity).despawn_recursive();
+        }
+    }
+}
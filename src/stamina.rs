@@ -0,0 +1,147 @@
+use crate::player::Player;
+use bevy::prelude::*;
+
+const DRAIN_RATE: f32 = 35.; // Stamina lost per second while sprinting.
+const REGEN_RATE: f32 = 20.; // Stamina gained per second while walking or idle.
+const EXHAUSTION_RECOVERY: f32 = 0.4; // Fraction of max stamina needed before sprinting again after running out.
+
+pub struct StaminaPlugin;
+
+impl Plugin for StaminaPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RestoreStaminaEvent>()
+            .add_systems(Startup, spawn_stamina_bar)
+            .add_systems(Update, update_stamina)
+            .add_systems(Update, restore_stamina)
+            .add_systems(Update, update_stamina_bar);
+    }
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct Stamina {
+    pub current: f32,
+    pub max: f32,
+    pub exhausted: bool, // Locked out of sprinting until stamina recovers.
+    pub sprinting: bool,
+}
+
+impl Stamina {
+    pub fn new(max: f32) -> Self {
+        Stamina {
+            current: max,
+            max,
+            exhausted: false,
+            sprinting: false,
+        }
+    }
+
+    // Called by movement each frame, returns whether the player actually gets to sprint.
+    pub fn try_sprint(&mut self, wants_to_sprint: bool) -> bool {
+        self.sprinting = wants_to_sprint && !self.exhausted && self.current > 0.;
+        self.sprinting
+    }
+
+    pub fn restore(&mut self, amount: f32) {
+        self.current = (self.current + amount).min(self.max);
+    }
+
+    pub fn fraction(&self) -> f32 {
+        self.current / self.max
+    }
+}
+
+// Sent by anything that gives stamina back, such as drinking a soda.
+#[derive(Event)]
+pub struct RestoreStaminaEvent {
+    pub amount: f32,
+}
+
+// Drain stamina while sprinting, regenerate otherwise.
+fn update_stamina(mut stamina_q: Query<&mut Stamina>, time: Res<Time>) {
+    let dt = time.delta_seconds();
+
+    for mut stamina in stamina_q.iter_mut() {
+        if stamina.sprinting {
+            stamina.current = (stamina.current - DRAIN_RATE * dt).max(0.);
+
+            if stamina.current == 0. {
+                stamina.exhausted = true;
+                stamina.sprinting = false;
+            }
+        } else {
+            let max = stamina.max;
+            stamina.current = (stamina.current + REGEN_RATE * dt).min(max);
+        }
+
+        if stamina.exhausted && stamina.fraction() >= EXHAUSTION_RECOVERY {
+            stamina.exhausted = false;
+        }
+    }
+}
+
+fn restore_stamina(
+    mut stamina_q: Query<&mut Stamina, With<Player>>,
+    mut restore_events: EventReader<RestoreStaminaEvent>,
+) {
+    let Ok(mut stamina) = stamina_q.get_single_mut() else {
+        return;
+    };
+
+    for ev in restore_events.iter() {
+        stamina.restore(ev.amount);
+    }
+}
+
+#[derive(Component)]
+struct StaminaBar;
+
+// Spawn stamina bar in top-right corner of screen.
+fn spawn_stamina_bar(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.),
+                right: Val::Px(10.),
+                width: Val::Px(120.),
+                height: Val::Px(10.),
+                border: UiRect::all(Val::Px(2.)),
+                ..default()
+            },
+            border_color: BorderColor(Color::WHITE),
+            background_color: BackgroundColor(Color::rgb(0.15, 0.15, 0.15)),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.),
+                        height: Val::Percent(100.),
+                        ..default()
+                    },
+                    background_color: BackgroundColor(Color::YELLOW),
+                    ..default()
+                })
+                .insert(StaminaBar);
+        });
+}
+
+// Resize bar to current stamina, and turn it red while exhausted.
+fn update_stamina_bar(
+    stamina_q: Query<&Stamina, (With<Player>, Changed<Stamina>)>,
+    mut bar_q: Query<(&mut Style, &mut BackgroundColor), With<StaminaBar>>,
+) {
+    let Ok(stamina) = stamina_q.get_single() else {
+        return;
+    };
+
+    for (mut style, mut color) in bar_q.iter_mut() {
+        style.width = Val::Percent(stamina.fraction() * 100.);
+        color.0 = if stamina.exhausted {
+            Color::RED
+        } else {
+            Color::YELLOW
+        };
+    }
+}
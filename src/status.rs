@@ -0,0 +1,224 @@
+use crate::player::Player;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+pub struct StatusPlugin;
+
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_status_text)
+            .add_systems(Update, (tick_status_effects, update_status_text).chain());
+    }
+}
+
+// What a status effect does while it lasts.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StatusKind {
+    Speed, // Multiplies movement speed by magnitude.
+}
+
+// What happens when an effect is applied while one of the same kind is already active.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stacking {
+    #[default]
+    Refresh, // Keep one, with the stronger magnitude and the longer time left.
+    Extend,  // Keep one, with the stronger magnitude, adding the new duration to time left.
+    Stack,   // Keep both, each running out on its own, with magnitudes multiplied together.
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub magnitude: f32,
+    pub remaining: f32, // Seconds until it wears off.
+    pub stacking: Stacking,
+}
+
+// Temporary effects on an entity, like a speed buff from eating something.
+#[derive(Component, Default, Clone, Debug)]
+pub struct StatusEffects {
+    effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    pub fn apply(&mut self, effect: StatusEffect) {
+        let existing = self.effects.iter_mut().find(|active| active.kind == effect.kind);
+
+        match (effect.stacking, existing) {
+            (Stacking::Refresh, Some(active)) => {
+                active.magnitude = active.magnitude.max(effect.magnitude);
+                active.remaining = active.remaining.max(effect.remaining);
+            }
+            (Stacking::Extend, Some(active)) => {
+                active.magnitude = active.magnitude.max(effect.magnitude);
+                active.remaining += effect.remaining;
+            }
+            _ => self.effects.push(effect),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &StatusEffect> {
+        self.effects.iter()
+    }
+
+    // Combined magnitude of every active effect of a kind, 1 if there are none.
+    pub fn multiplier(&self, kind: StatusKind) -> f32 {
+        self.effects
+            .iter()
+            .filter(|effect| effect.kind == kind)
+            .map(|effect| effect.magnitude)
+            .product()
+    }
+
+    // Count down, dropping effects that have worn off.
+    pub fn tick(&mut self, dt: f32) {
+        for effect in self.effects.iter_mut() {
+            effect.remaining -= dt;
+        }
+
+        self.effects.retain(|effect| effect.remaining > 0.);
+    }
+}
+
+fn tick_status_effects(mut status_q: Query<&mut StatusEffects>, time: Res<Time>) {
+    let dt = time.delta_seconds();
+
+    for mut status in status_q.iter_mut() {
+        if !status.effects.is_empty() {
+            status.tick(dt);
+        }
+    }
+}
+
+#[derive(Component)]
+struct StatusText;
+
+// Spawn list of the player's active effects under the stamina bar.
+fn spawn_status_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 16.,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(28.),
+                right: Val::Px(10.),
+                ..default()
+            },
+            ..default()
+        },
+        StatusText,
+    ));
+}
+
+// One line per effect, like "Speed x1.5 4s".
+fn update_status_text(
+    status_q: Query<&StatusEffects, (With<Player>, Changed<StatusEffects>)>,
+    mut text_q: Query<&mut Text, With<StatusText>>,
+) {
+    let Ok(status) = status_q.get_single() else {
+        return;
+    };
+
+    let lines: Vec<String> = status
+        .iter()
+        .map(|effect| format!("{:?} x{} {}s", effect.kind, effect.magnitude, effect.remaining.ceil()))
+        .collect();
+
+    for mut text in text_q.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::{Direction, PlayerAnimationType};
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    fn speed(magnitude: f32, remaining: f32, stacking: Stacking) -> StatusEffect {
+        StatusEffect {
+            kind: StatusKind::Speed,
+            magnitude,
+            remaining,
+            stacking,
+        }
+    }
+
+    #[test]
+    fn refresh_keeps_strongest_and_longest() {
+        let mut status = StatusEffects::default();
+        status.apply(speed(1.5, 5., Stacking::Refresh));
+        status.tick(3.);
+        status.apply(speed(1.2, 4., Stacking::Refresh));
+
+        assert_eq!(status.iter().count(), 1);
+        assert_eq!(status.multiplier(StatusKind::Speed), 1.5);
+        assert_eq!(status.iter().next().unwrap().remaining, 4.);
+    }
+
+    #[test]
+    fn extend_adds_time() {
+        let mut status = StatusEffects::default();
+        status.apply(speed(1.5, 5., Stacking::Extend));
+        status.tick(3.);
+        status.apply(speed(1.5, 5., Stacking::Extend));
+
+        assert_eq!(status.iter().count(), 1);
+        assert_eq!(status.iter().next().unwrap().remaining, 7.);
+    }
+
+    #[test]
+    fn stacked_effects_multiply_and_expire_separately() {
+        let mut status = StatusEffects::default();
+        status.apply(speed(1.5, 2., Stacking::Stack));
+        status.apply(speed(2., 4., Stacking::Stack));
+        assert_eq!(status.multiplier(StatusKind::Speed), 3.);
+
+        status.tick(3.);
+        assert_eq!(status.multiplier(StatusKind::Speed), 2.);
+
+        status.tick(1.);
+        assert_eq!(status.iter().count(), 0);
+        assert_eq!(status.multiplier(StatusKind::Speed), 1.);
+    }
+
+    #[test]
+    fn effects_wear_off_over_time() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(0.1)))
+            .add_plugins(StatusPlugin);
+
+        let mut status = StatusEffects::default();
+        status.apply(speed(1.5, 0.45, Stacking::Refresh));
+        let player = Player {
+            animation: PlayerAnimationType::Idle(Direction::South),
+            direction: Direction::South,
+        };
+        let entity = app.world.spawn((player, status)).id();
+
+        let text = |app: &mut App| {
+            let mut text_q = app.world.query_filtered::<&Text, With<StatusText>>();
+            text_q.single(&app.world).sections[0].value.clone()
+        };
+
+        app.update(); // First update has no time delta.
+        assert_eq!(text(&mut app), "Speed x1.5 1s");
+        for _ in 0..4 {
+            app.update();
+        }
+        assert_eq!(app.world.get::<StatusEffects>(entity).unwrap().multiplier(StatusKind::Speed), 1.5);
+
+        app.update();
+        assert_eq!(app.world.get::<StatusEffects>(entity).unwrap().multiplier(StatusKind::Speed), 1.);
+        assert_eq!(text(&mut app), "");
+    }
+}
@@ -0,0 +1,346 @@
+use crate::animator::LoopMode;
+use bevy::prelude::*;
+use std::f32::consts::PI;
+
+pub struct TweenPlugin;
+
+impl Plugin for TweenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TweenCompleted>()
+            .add_systems(Update, (tick_tweens, apply_tweens).chain());
+    }
+}
+
+// Shape of a tween's progress over time.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Ease {
+    #[default]
+    Linear,
+    #[allow(dead_code)] // Part of the set, even if nothing in the game eases in yet.
+    QuadIn,
+    QuadOut,
+    #[allow(dead_code)]
+    QuadInOut,
+    SineInOut,
+    BackOut, // Overshoots a little then settles, for pops.
+}
+
+impl Ease {
+    // Map linear progress from 0 to 1 onto the curve.
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Ease::Linear => t,
+            Ease::QuadIn => t * t,
+            Ease::QuadOut => 1. - (1. - t) * (1. - t),
+            Ease::QuadInOut => {
+                if t < 0.5 {
+                    2. * t * t
+                } else {
+                    1. - (-2. * t + 2.).powi(2) / 2.
+                }
+            }
+            Ease::SineInOut => -((PI * t).cos() - 1.) / 2.,
+            Ease::BackOut => {
+                const OVERSHOOT: f32 = 1.70158;
+                let t = t - 1.;
+                1. + (OVERSHOOT + 1.) * t * t * t + OVERSHOOT * t * t
+            }
+        }
+    }
+}
+
+// Property a tween animates, from start to end.
+#[derive(Clone, Copy, Debug)]
+pub enum Lens {
+    Translation(Vec3, Vec3),
+    Scale(Vec3, Vec3),
+    #[allow(dead_code)]
+    Rotation(Quat, Quat),
+    Color(Color, Color), // Sprite, atlas sprite or UI background color, whichever the entity has.
+}
+
+// Animates an entity's transform or color over time, independent of frame rate.
+#[derive(Component, Clone, Debug)]
+pub struct Tween {
+    pub lens: Lens,
+    pub duration: f32, // Seconds for one pass from start to end.
+    pub ease: Ease,
+    pub loop_mode: LoopMode, // PingPong plays back and forth like a yoyo.
+    pub elapsed: f32,
+    pub finished: bool, // Set once a tween that plays once reaches its end.
+}
+
+impl Tween {
+    pub fn new(lens: Lens, duration: f32) -> Self {
+        Tween {
+            lens,
+            duration,
+            ease: Ease::Linear,
+            loop_mode: LoopMode::Once,
+            elapsed: 0.,
+            finished: false,
+        }
+    }
+
+    pub fn with_ease(mut self, ease: Ease) -> Self {
+        self.ease = ease;
+        self
+    }
+
+    pub fn with_loop_mode(mut self, loop_mode: LoopMode) -> Self {
+        self.loop_mode = loop_mode;
+        self
+    }
+
+    // Start this many seconds in, so entities sharing a tween don't all move in lockstep.
+    pub fn with_phase(mut self, phase: f32) -> Self {
+        self.elapsed = phase;
+        self
+    }
+
+    // Number of passes completed so far.
+    fn passes(&self) -> u32 {
+        if self.duration <= 0. {
+            return 0;
+        }
+
+        (self.elapsed / self.duration) as u32
+    }
+
+    // Eased progress from start (0) to end (1).
+    pub fn progress(&self) -> f32 {
+        if self.duration <= 0. {
+            return 1.;
+        }
+
+        let pass = self.elapsed / self.duration;
+        let t = match self.loop_mode {
+            LoopMode::Once => pass.min(1.),
+            LoopMode::Loop => pass.fract(),
+            LoopMode::PingPong => {
+                if self.passes().is_multiple_of(2) {
+                    pass.fract()
+                } else {
+                    1. - pass.fract()
+                }
+            }
+        };
+
+        self.ease.apply(t)
+    }
+}
+
+// Sent whenever a tween finishes a pass. Tweens that play once only send it the one time.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct TweenCompleted {
+    pub entity: Entity,
+}
+
+fn tick_tweens(
+    mut tween_q: Query<(Entity, &mut Tween)>,
+    mut completed_events: EventWriter<TweenCompleted>,
+    time: Res<Time>,
+) {
+    for (entity, mut tween) in tween_q.iter_mut() {
+        if tween.finished {
+            continue;
+        }
+
+        let passes = tween.passes();
+        tween.elapsed += time.delta_seconds();
+
+        if tween.loop_mode == LoopMode::Once && tween.elapsed >= tween.duration {
+            tween.elapsed = tween.duration;
+            tween.finished = true;
+        }
+
+        for _ in passes..tween.passes() {
+            completed_events.send(TweenCompleted { entity });
+        }
+    }
+}
+
+// Tweens, with everything they might animate.
+type TweenQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Tween,
+        Option<&'static mut Transform>,
+        Option<&'static mut Sprite>,
+        Option<&'static mut TextureAtlasSprite>,
+        Option<&'static mut BackgroundColor>,
+    ),
+>;
+
+fn apply_tweens(mut tween_q: TweenQuery) {
+    for (tween, pos, sprite, atlas_sprite, background) in tween_q.iter_mut() {
+        let t = tween.progress();
+
+        match tween.lens {
+            Lens::Translation(start, end) => {
+                if let Some(mut pos) = pos {
+                    pos.translation = start.lerp(end, t);
+                }
+            }
+            Lens::Scale(start, end) => {
+                if let Some(mut pos) = pos {
+                    pos.scale = start.lerp(end, t);
+                }
+            }
+            Lens::Rotation(start, end) => {
+                if let Some(mut pos) = pos {
+                    pos.rotation = start.slerp(end, t);
+                }
+            }
+            Lens::Color(start, end) => {
+                let color = lerp_color(start, end, t);
+
+                if let Some(mut sprite) = sprite {
+                    sprite.color = color;
+                }
+                if let Some(mut atlas_sprite) = atlas_sprite {
+                    atlas_sprite.color = color;
+                }
+                if let Some(mut background) = background {
+                    background.0 = color;
+                }
+            }
+        }
+    }
+}
+
+fn lerp_color(start: Color, end: Color, t: f32) -> Color {
+    let start = Vec4::from(start.as_rgba_f32());
+    let end = Vec4::from(end.as_rgba_f32());
+    let [r, g, b, a] = start.lerp(end, t).to_array();
+    Color::rgba(r, g, b, a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    fn test_app(frame_time: f32) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+                frame_time,
+            )))
+            .add_plugins(TweenPlugin);
+        app.update(); // First update has no time delta.
+        app
+    }
+
+    fn run_for(app: &mut App, frame_time: f32, seconds: f32) {
+        for _ in 0..(seconds / frame_time).round() as usize {
+            app.update();
+        }
+    }
+
+    fn bob() -> Tween {
+        Tween::new(Lens::Translation(Vec3::ZERO, Vec3::new(0., 10., 0.)), 1.)
+            .with_loop_mode(LoopMode::PingPong)
+    }
+
+    #[test]
+    fn frame_rate_independent() {
+        let mut slow = test_app(0.1);
+        let mut fast = test_app(0.01);
+        let slow_entity = slow.world.spawn((Transform::default(), bob())).id();
+        let fast_entity = fast.world.spawn((Transform::default(), bob())).id();
+
+        run_for(&mut slow, 0.1, 0.5);
+        run_for(&mut fast, 0.01, 0.5);
+
+        let slow_y = slow.world.get::<Transform>(slow_entity).unwrap().translation.y;
+        let fast_y = fast.world.get::<Transform>(fast_entity).unwrap().translation.y;
+        assert!((slow_y - 5.).abs() < 0.01);
+        assert!((fast_y - slow_y).abs() < 0.01);
+    }
+
+    #[test]
+    fn ping_pong_returns_and_phase_offsets() {
+        let mut app = test_app(0.1);
+        let entity = app.world.spawn((Transform::default(), bob())).id();
+        let offset = app.world.spawn((Transform::default(), bob().with_phase(1.))).id();
+
+        run_for(&mut app, 0.1, 1.5);
+
+        // Half way back down, while the offset one is half way back up.
+        let y = |app: &App, entity| app.world.get::<Transform>(entity).unwrap().translation.y;
+        assert!((y(&app, entity) - 5.).abs() < 0.01);
+        assert!((y(&app, offset) - 5.).abs() < 0.01);
+
+        run_for(&mut app, 0.1, 0.5);
+        assert!(y(&app, entity).abs() < 0.01);
+        assert!((y(&app, offset) - 10.).abs() < 0.01);
+    }
+
+    #[test]
+    fn once_finishes_and_sends_event() {
+        let mut app = test_app(0.1);
+        let entity = app
+            .world
+            .spawn((
+                Sprite::default(),
+                Tween::new(Lens::Color(Color::WHITE, Color::rgba(1., 1., 1., 0.)), 0.5)
+                    .with_ease(Ease::QuadOut),
+            ))
+            .id();
+
+        let mut completed = 0;
+        for _ in 0..10 {
+            app.update();
+            let events = app.world.resource::<Events<TweenCompleted>>();
+            completed += events
+                .iter_current_update_events()
+                .filter(|ev| ev.entity == entity)
+                .count();
+        }
+
+        assert_eq!(completed, 1);
+        assert!(app.world.get::<Tween>(entity).unwrap().finished);
+        assert_eq!(app.world.get::<Sprite>(entity).unwrap().color.a(), 0.);
+    }
+
+    #[test]
+    fn rotation_slerps_then_finishes() {
+        let mut app = test_app(0.1);
+        let end = Quat::from_rotation_z(PI / 2.);
+        let entity = app
+            .world
+            .spawn((
+                Transform::default(),
+                Tween::new(Lens::Rotation(Quat::IDENTITY, end), 1.).with_ease(Ease::QuadInOut),
+            ))
+            .id();
+
+        // Eased curve is symmetric, so half way through time is half way round.
+        run_for(&mut app, 0.1, 0.5);
+        let rotation = app.world.get::<Transform>(entity).unwrap().rotation;
+        assert!(rotation.abs_diff_eq(Quat::from_rotation_z(PI / 4.), 1e-5));
+
+        run_for(&mut app, 0.1, 0.6);
+        let rotation = app.world.get::<Transform>(entity).unwrap().rotation;
+        assert!(rotation.abs_diff_eq(end, 1e-5));
+        assert!(app.world.get::<Tween>(entity).unwrap().finished);
+    }
+
+    #[test]
+    fn eases_start_and_end_in_place() {
+        for ease in [
+            Ease::Linear,
+            Ease::QuadIn,
+            Ease::QuadOut,
+            Ease::QuadInOut,
+            Ease::SineInOut,
+            Ease::BackOut,
+        ] {
+            assert!(ease.apply(0.).abs() < 1e-5, "{:?}", ease);
+            assert!((ease.apply(1.) - 1.).abs() < 1e-5, "{:?}", ease);
+        }
+    }
+}